@@ -0,0 +1,105 @@
+//! Compares fetching a deep page with a cursor against `OFFSET`, with
+//! [`Paginator::offset_mode`], on an in-memory SQLite table. Run with
+//! `cargo bench --features sqlite`.
+//!
+//! A cursor seeks to the page through the primary key index, while `OFFSET`
+//! reads and discards every row before the page, so the gap grows with the page
+//! number: shallow pages cost about the same either way.
+
+use criterion::{BenchmarkId, Criterion, criterion_group, criterion_main};
+use sqlx::{FromRow, QueryBuilder, Sqlite, SqlitePool, sqlite::SqlitePoolOptions};
+use sqlx_cursor_paginator::{PaginationRequest, Paginator, SortOrder, encode_cursor};
+use tokio::runtime::Builder;
+
+#[derive(FromRow)]
+#[allow(dead_code)]
+struct Task {
+    id: i64,
+    name: String,
+}
+
+const ROWS: i64 = 500_000;
+const LIMIT: u32 = 20;
+
+async fn setup() -> SqlitePool {
+    // every connection to an in-memory database gets its own database
+    let pool = SqlitePoolOptions::new()
+        .max_connections(1)
+        .connect("sqlite::memory:")
+        .await
+        .unwrap();
+
+    sqlx::query("CREATE TABLE tasks (id INTEGER PRIMARY KEY, name TEXT NOT NULL)")
+        .execute(&pool)
+        .await
+        .unwrap();
+
+    // a recursive CTE seeds the rows in one statement
+    sqlx::query(
+        "WITH RECURSIVE ids(id) AS (SELECT 1 UNION ALL SELECT id + 1 FROM ids WHERE id < ?) \
+         INSERT INTO tasks (id, name) SELECT id, 'task ' || id FROM ids",
+    )
+    .bind(ROWS)
+    .execute(&pool)
+    .await
+    .unwrap();
+
+    pool
+}
+
+fn tasks(query: &mut QueryBuilder<Sqlite>) {
+    query.push("SELECT id, name FROM tasks WHERE TRUE");
+}
+
+fn paginator(request: &PaginationRequest) -> Paginator<Task, Sqlite> {
+    Paginator::<Task, Sqlite>::new()
+        .single_key("id")
+        .retrieve_key(|task: &Task| task.id.to_string())
+        // the keyset pages start from cursors built with encode_cursor
+        .accept_legacy_cursors(true)
+        .request(request)
+}
+
+fn deep_pages(c: &mut Criterion) {
+    let runtime = Builder::new_current_thread().enable_all().build().unwrap();
+    let pool = runtime.block_on(setup());
+
+    let mut group = c.benchmark_group("page");
+    for page in [1, 100, 10_000] {
+        let request = PaginationRequest {
+            limit: Some(LIMIT),
+            sort_order: Some(SortOrder::Asc),
+            ..Default::default()
+        };
+        // the cursor of the last row of the previous page
+        let before = (page - 1) as i64 * LIMIT as i64;
+        let keyset = PaginationRequest {
+            cursor: Some(encode_cursor(&[before.to_string()])),
+            ..request.clone()
+        };
+        let offset = PaginationRequest {
+            page: Some(page),
+            ..request
+        };
+
+        for (name, request) in [("keyset", keyset), ("offset", offset)] {
+            group.bench_with_input(BenchmarkId::new(name, page), &request, |b, request| {
+                b.to_async(&runtime).iter(|| async {
+                    let mut paginator = paginator(request);
+                    if request.page.is_some() {
+                        paginator = paginator.offset_mode();
+                    }
+                    let res = paginator
+                        .paginate_single_with::<i64, _>(&pool, tasks)
+                        .await
+                        .unwrap();
+                    assert_eq!(res.data.first().map(|task| task.id), Some(before + 1));
+                })
+            });
+        }
+    }
+    group.finish();
+}
+
+criterion_group!(benches, deep_pages);
+criterion_main!(benches);
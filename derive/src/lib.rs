@@ -0,0 +1,54 @@
+use proc_macro::TokenStream;
+use quote::quote;
+use syn::{Data, DeriveInput, Fields, parse_macro_input};
+
+/// Derives `KeyParse` for a newtype like `struct UserId(Uuid)`, delegating to the
+/// `KeyParse` of the wrapped type.
+#[proc_macro_derive(KeyParse)]
+pub fn derive_key_parse(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+
+    match expand(input) {
+        Ok(tokens) => tokens.into(),
+        Err(err) => err.to_compile_error().into(),
+    }
+}
+
+fn expand(input: DeriveInput) -> syn::Result<proc_macro2::TokenStream> {
+    let fields = match &input.data {
+        Data::Struct(data) => &data.fields,
+        _ => {
+            return Err(syn::Error::new_spanned(
+                &input.ident,
+                "KeyParse can only be derived for tuple structs with a single field",
+            ));
+        }
+    };
+
+    let inner = match fields {
+        Fields::Unnamed(unnamed) if unnamed.unnamed.len() == 1 => &unnamed.unnamed[0].ty,
+        _ => {
+            return Err(syn::Error::new_spanned(
+                fields,
+                "KeyParse can only be derived for tuple structs with a single field",
+            ));
+        }
+    };
+
+    let name = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+
+    Ok(quote! {
+        impl #impl_generics ::sqlx_cursor_paginator::KeyParse for #name #ty_generics #where_clause {
+            fn parse_key(
+                key: ::std::string::String,
+            ) -> ::std::result::Result<Self, ::sqlx_cursor_paginator::PaginationError> {
+                <#inner as ::sqlx_cursor_paginator::KeyParse>::parse_key(key).map(#name)
+            }
+
+            fn format_key(&self) -> ::std::string::String {
+                <#inner as ::sqlx_cursor_paginator::KeyParse>::format_key(&self.0)
+            }
+        }
+    })
+}
@@ -0,0 +1,7 @@
+#[test]
+fn ui() {
+    let t = trybuild::TestCases::new();
+    t.pass("tests/ui/newtype.rs");
+    t.compile_fail("tests/ui/two_fields.rs");
+    t.compile_fail("tests/ui/named_field.rs");
+}
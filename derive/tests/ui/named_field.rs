@@ -0,0 +1,6 @@
+use sqlx_cursor_paginator::KeyParse;
+
+#[derive(KeyParse)]
+struct UserId { id: i64 }
+
+fn main() {}
@@ -0,0 +1,12 @@
+use sqlx_cursor_paginator::KeyParse;
+
+#[derive(Debug, KeyParse, PartialEq)]
+struct UserId(i64);
+
+fn main() {
+    let id = UserId::parse_key(String::from("42")).unwrap();
+    assert_eq!(id, UserId(42));
+    assert_eq!(id.format_key(), "42");
+
+    assert!(UserId::parse_key(String::from("abc")).is_err());
+}
@@ -0,0 +1,6 @@
+use sqlx_cursor_paginator::KeyParse;
+
+#[derive(KeyParse)]
+struct Range(i64, i64);
+
+fn main() {}
@@ -0,0 +1,39 @@
+//! Feeds arbitrary cursors to the cursor decoding, which must reject them with
+//! [`PaginationError::InvalidCursor`] rather than panic. Run from the crate root
+//! with a nightly toolchain and `cargo install cargo-fuzz`:
+//!
+//! ```sh
+//! cargo +nightly fuzz run decode_cursor
+//! ```
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use sqlx_cursor_paginator::{MessagePackCodec, PaginationError, Paginator, decode_cursor};
+
+fn check<K>(res: Result<K, PaginationError>) {
+    match res {
+        Ok(_) | Err(PaginationError::InvalidCursor(_)) => {}
+        Err(err) => panic!("unexpected error: {:?}", err),
+    }
+}
+
+fuzz_target!(|data: &[u8]| {
+    let cursor = String::from_utf8_lossy(data);
+    check(decode_cursor(&cursor));
+
+    let paginators = [
+        Paginator::<()>::new().keys("created_at", "id"),
+        Paginator::<()>::new()
+            .keys("created_at", "id")
+            .signing_key(b"secret"),
+        Paginator::<()>::new()
+            .single_key("id")
+            .cursor_codec(MessagePackCodec),
+    ];
+    for paginator in paginators {
+        check(paginator.validate_cursor(&cursor));
+        // most arbitrary strings aren't base64, so the bytes are fed as a payload too
+        check(paginator.validate_cursor(&paginator.cursor_from_bytes(data)));
+    }
+});
@@ -0,0 +1,157 @@
+use std::future::Future;
+
+use sqlx::{ColumnIndex, Database, Decode, FromRow, QueryBuilder, Row, Type};
+
+/// Column the `COUNT(*) OVER ()` of [`crate::Paginator::detect_next_via_window`] is
+/// selected as.
+pub const WINDOW_COUNT_COLUMN: &str = "paginator_count";
+
+/// A database the paginator can run its queries against.
+///
+/// The keyset SQL itself is shared between databases since [`QueryBuilder`] already
+/// emits the right placeholders (`$1` for Postgres, `?` for MySQL); implementors only
+/// run the built queries, on the connection the paginator acquired, e.g. from a pool
+/// or a transaction.
+pub trait Backend: Database {
+    /// Whether NULLs sort before every other value when no `NULLS FIRST`/`NULLS LAST`
+    /// is given, as on MySQL and SQLite, rather than after as on Postgres.
+    const NULLS_SMALLEST: bool = false;
+
+    /// Whether `ORDER BY` understands `NULLS FIRST`/`NULLS LAST`, which
+    /// [`crate::Paginator::nulls`] emits.
+    const SUPPORTS_NULLS_ORDER: bool = true;
+
+    fn fetch_all<'q, 'args, T>(
+        query: &'q mut QueryBuilder<'args, Self>,
+        conn: &'q mut Self::Connection,
+    ) -> impl Future<Output = Result<Vec<T>, sqlx::Error>> + Send + 'q
+    where
+        T: for<'r> FromRow<'r, Self::Row> + Send + Unpin + 'q;
+
+    fn fetch_count<'q, 'args>(
+        query: &'q mut QueryBuilder<'args, Self>,
+        conn: &'q mut Self::Connection,
+    ) -> impl Future<Output = Result<i64, sqlx::Error>> + Send + 'q;
+
+    /// Fetches the rows along with their [`WINDOW_COUNT_COLUMN`].
+    fn fetch_all_counted<'q, 'args, T>(
+        query: &'q mut QueryBuilder<'args, Self>,
+        conn: &'q mut Self::Connection,
+    ) -> impl Future<Output = Result<Vec<(T, i64)>, sqlx::Error>> + Send + 'q
+    where
+        T: for<'r> FromRow<'r, Self::Row> + Send + Unpin + 'q;
+}
+
+fn counted<R, T>(rows: Vec<R>) -> Result<Vec<(T, i64)>, sqlx::Error>
+where
+    R: Row,
+    T: for<'r> FromRow<'r, R>,
+    i64: for<'r> Decode<'r, R::Database> + Type<R::Database>,
+    for<'c> &'c str: ColumnIndex<R>,
+{
+    rows.iter()
+        .map(|row| Ok((T::from_row(row)?, row.try_get(WINDOW_COUNT_COLUMN)?)))
+        .collect()
+}
+
+impl Backend for sqlx::Postgres {
+    fn fetch_all<'q, 'args, T>(
+        query: &'q mut QueryBuilder<'args, Self>,
+        conn: &'q mut Self::Connection,
+    ) -> impl Future<Output = Result<Vec<T>, sqlx::Error>> + Send + 'q
+    where
+        T: for<'r> FromRow<'r, Self::Row> + Send + Unpin + 'q,
+    {
+        query.build_query_as::<T>().fetch_all(conn)
+    }
+
+    fn fetch_count<'q, 'args>(
+        query: &'q mut QueryBuilder<'args, Self>,
+        conn: &'q mut Self::Connection,
+    ) -> impl Future<Output = Result<i64, sqlx::Error>> + Send + 'q {
+        query.build_query_scalar::<i64>().fetch_one(conn)
+    }
+
+    fn fetch_all_counted<'q, 'args, T>(
+        query: &'q mut QueryBuilder<'args, Self>,
+        conn: &'q mut Self::Connection,
+    ) -> impl Future<Output = Result<Vec<(T, i64)>, sqlx::Error>> + Send + 'q
+    where
+        T: for<'r> FromRow<'r, Self::Row> + Send + Unpin + 'q,
+    {
+        let query = query.build();
+        async move { counted(query.fetch_all(conn).await?) }
+    }
+}
+
+/// MySQL has no `NULLS FIRST`/`NULLS LAST`, so [`crate::NullsOrder`] can't be used
+/// with it; paginating with [`crate::Paginator::nulls`] fails with a
+/// [`crate::PaginationError::Configuration`].
+#[cfg(feature = "mysql")]
+impl Backend for sqlx::MySql {
+    const NULLS_SMALLEST: bool = true;
+    const SUPPORTS_NULLS_ORDER: bool = false;
+
+    fn fetch_all<'q, 'args, T>(
+        query: &'q mut QueryBuilder<'args, Self>,
+        conn: &'q mut Self::Connection,
+    ) -> impl Future<Output = Result<Vec<T>, sqlx::Error>> + Send + 'q
+    where
+        T: for<'r> FromRow<'r, Self::Row> + Send + Unpin + 'q,
+    {
+        query.build_query_as::<T>().fetch_all(conn)
+    }
+
+    fn fetch_count<'q, 'args>(
+        query: &'q mut QueryBuilder<'args, Self>,
+        conn: &'q mut Self::Connection,
+    ) -> impl Future<Output = Result<i64, sqlx::Error>> + Send + 'q {
+        query.build_query_scalar::<i64>().fetch_one(conn)
+    }
+
+    fn fetch_all_counted<'q, 'args, T>(
+        query: &'q mut QueryBuilder<'args, Self>,
+        conn: &'q mut Self::Connection,
+    ) -> impl Future<Output = Result<Vec<(T, i64)>, sqlx::Error>> + Send + 'q
+    where
+        T: for<'r> FromRow<'r, Self::Row> + Send + Unpin + 'q,
+    {
+        let query = query.build();
+        async move { counted(query.fetch_all(conn).await?) }
+    }
+}
+
+/// SQLite has no native UUID or timestamp types; keys of those kinds bind as
+/// whatever sqlx maps them to (e.g. TEXT), so they must be stored the same way.
+#[cfg(feature = "sqlite")]
+impl Backend for sqlx::Sqlite {
+    const NULLS_SMALLEST: bool = true;
+
+    fn fetch_all<'q, 'args, T>(
+        query: &'q mut QueryBuilder<'args, Self>,
+        conn: &'q mut Self::Connection,
+    ) -> impl Future<Output = Result<Vec<T>, sqlx::Error>> + Send + 'q
+    where
+        T: for<'r> FromRow<'r, Self::Row> + Send + Unpin + 'q,
+    {
+        query.build_query_as::<T>().fetch_all(conn)
+    }
+
+    fn fetch_count<'q, 'args>(
+        query: &'q mut QueryBuilder<'args, Self>,
+        conn: &'q mut Self::Connection,
+    ) -> impl Future<Output = Result<i64, sqlx::Error>> + Send + 'q {
+        query.build_query_scalar::<i64>().fetch_one(conn)
+    }
+
+    fn fetch_all_counted<'q, 'args, T>(
+        query: &'q mut QueryBuilder<'args, Self>,
+        conn: &'q mut Self::Connection,
+    ) -> impl Future<Output = Result<Vec<(T, i64)>, sqlx::Error>> + Send + 'q
+    where
+        T: for<'r> FromRow<'r, Self::Row> + Send + Unpin + 'q,
+    {
+        let query = query.build();
+        async move { counted(query.fetch_all(conn).await?) }
+    }
+}
@@ -0,0 +1,847 @@
+use std::sync::Arc;
+
+use base64::{
+    Engine,
+    engine::GeneralPurpose,
+    prelude::{BASE64_STANDARD, BASE64_STANDARD_NO_PAD, BASE64_URL_SAFE, BASE64_URL_SAFE_NO_PAD},
+};
+#[cfg(feature = "crypto")]
+use chacha20poly1305::{
+    ChaCha20Poly1305, Key, Nonce,
+    aead::{Aead, AeadCore, KeyInit, OsRng},
+};
+use hmac::{Hmac, Mac};
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+
+use crate::{CursorError, PaginationError};
+
+type HmacSha256 = Hmac<Sha256>;
+
+/// Encodes key values into a cursor the way [`crate::Paginator`] does (unsigned
+/// and unencrypted), e.g. to start paginating from a known record. The cursor
+/// carries no sort, so paginators only take it with
+/// [`crate::Paginator::accept_legacy_cursors`]; [`crate::Paginator::cursor_for`]
+/// issues one under the paginator's sort.
+pub fn encode_cursor(keys: &[String]) -> String {
+    // serializing a list of strings can't fail
+    CursorConfig::default()
+        .encode(keys.iter().cloned().map(Some).collect())
+        .unwrap_or_default()
+}
+
+/// Decodes an unsigned, unencrypted cursor back into its key values.
+pub fn decode_cursor(cursor: &str) -> Result<Vec<String>, PaginationError> {
+    let values = CursorConfig::default().decode_values(cursor.to_string())?;
+
+    match values.into_iter().collect::<Option<Vec<String>>>() {
+        Some(values) => Ok(values),
+        None => Err(PaginationError::InvalidCursor(CursorError::NullKey)),
+    }
+}
+
+/// The cursor of the page to request, [`Cursor::first`] for the first page, set on a
+/// request with [`crate::PaginationRequest::with_cursor`].
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct Cursor(Option<String>);
+
+impl Cursor {
+    /// The first page, requested without a cursor.
+    pub fn first() -> Cursor {
+        Cursor(None)
+    }
+
+    /// A cursor returned by a previous page, e.g. its `next_cursor`.
+    pub fn new(cursor: impl Into<String>) -> Cursor {
+        Cursor(Some(cursor.into()))
+    }
+
+    pub fn is_first(&self) -> bool {
+        self.0.is_none()
+    }
+
+    pub fn into_inner(self) -> Option<String> {
+        self.0
+    }
+}
+
+impl From<Option<String>> for Cursor {
+    fn from(cursor: Option<String>) -> Cursor {
+        Cursor(cursor)
+    }
+}
+
+/// Version byte prefixed to every cursor, so the encoding can evolve without
+/// misparsing cursors that were already handed out.
+///
+/// Version 1 is a bare JSON array of the key values, version 2 a JSON
+/// [`CursorPayload`]. Other versions are picked by the [`CursorCodec`].
+const CURSOR_VERSION: u8 = 2;
+
+const CURSOR_VERSION_1: u8 = 1;
+
+/// What a cursor carries.
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct CursorPayload {
+    /// Key values of the row the cursor points at, `None` for NULL.
+    #[serde(rename = "k")]
+    pub keys: Vec<Option<String>>,
+    /// Sort the cursor was issued under, e.g. `created_at desc,id desc`.
+    #[serde(rename = "s", default, skip_serializing_if = "Option::is_none")]
+    pub sort: Option<String>,
+    /// Page size the cursor was issued with, to continue with by default, for
+    /// self-describing cursors.
+    #[serde(rename = "l", default, skip_serializing_if = "Option::is_none")]
+    pub limit: Option<u32>,
+}
+
+/// Serializes the [`CursorPayload`] before it's signed, encrypted and base64
+/// encoded. [`JsonCodec`] by default.
+///
+/// Every codec writes its own version byte, so that cursors issued before
+/// switching codecs still decode: JSON cursors always do, whichever codec is
+/// picked. Versions 1 and 2 are JSON's.
+pub trait CursorCodec: Send + Sync {
+    fn version(&self) -> u8;
+
+    fn encode(&self, payload: &CursorPayload) -> Result<Vec<u8>, PaginationError>;
+
+    fn decode(&self, bytes: &[u8]) -> Result<CursorPayload, PaginationError>;
+}
+
+/// Encodes the payload as `{"k": [k1, k2, ...], "s": sort}`.
+pub struct JsonCodec;
+
+impl CursorCodec for JsonCodec {
+    fn version(&self) -> u8 {
+        CURSOR_VERSION
+    }
+
+    fn encode(&self, payload: &CursorPayload) -> Result<Vec<u8>, PaginationError> {
+        match serde_json::to_vec(payload) {
+            Ok(bytes) => Ok(bytes),
+            Err(err) => Err(PaginationError::Serialization(format!(
+                "failed to serialize cursor: {}",
+                err
+            ))),
+        }
+    }
+
+    fn decode(&self, bytes: &[u8]) -> Result<CursorPayload, PaginationError> {
+        match serde_json::from_slice(bytes) {
+            Ok(payload) => Ok(payload),
+            Err(_) => Err(PaginationError::InvalidCursor(CursorError::InvalidPayload)),
+        }
+    }
+}
+
+/// Encodes the payload as a MessagePack array `[[k1, k2, ...], sort]`, or
+/// `[[k1, k2, ...], sort, limit]` with a limit, which is shorter than JSON,
+/// especially for many keys.
+#[cfg(feature = "msgpack")]
+pub struct MessagePackCodec;
+
+#[cfg(feature = "msgpack")]
+impl CursorCodec for MessagePackCodec {
+    fn version(&self) -> u8 {
+        3
+    }
+
+    fn encode(&self, payload: &CursorPayload) -> Result<Vec<u8>, PaginationError> {
+        let encoded = match payload.limit {
+            Some(limit) => rmp_serde::to_vec(&(&payload.keys, &payload.sort, limit)),
+            None => rmp_serde::to_vec(&(&payload.keys, &payload.sort)),
+        };
+        match encoded {
+            Ok(bytes) => Ok(bytes),
+            Err(err) => Err(PaginationError::Serialization(format!(
+                "failed to serialize cursor: {}",
+                err
+            ))),
+        }
+    }
+
+    fn decode(&self, bytes: &[u8]) -> Result<CursorPayload, PaginationError> {
+        if let Ok((keys, sort, limit)) = rmp_serde::from_slice(bytes) {
+            return Ok(CursorPayload {
+                keys,
+                sort,
+                limit: Some(limit),
+            });
+        }
+        match rmp_serde::from_slice(bytes) {
+            Ok((keys, sort)) => Ok(CursorPayload {
+                keys,
+                sort,
+                limit: None,
+            }),
+            Err(_) => Err(PaginationError::InvalidCursor(CursorError::InvalidPayload)),
+        }
+    }
+}
+
+/// Length of the HMAC-SHA256 tag appended to signed cursors.
+const SIGNATURE_LEN: usize = 32;
+
+/// Length of the ChaCha20-Poly1305 nonce prepended to encrypted cursors.
+#[cfg(feature = "crypto")]
+const NONCE_LEN: usize = 12;
+
+/// Base64 alphabet and padding of cursors.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub enum CursorEncoding {
+    /// URL-safe alphabet with padding.
+    #[default]
+    UrlSafe,
+    UrlSafeNoPad,
+    /// Standard alphabet with padding, for clients with older decoders.
+    Standard,
+    StandardNoPad,
+}
+
+impl CursorEncoding {
+    fn engine(&self) -> &'static GeneralPurpose {
+        match self {
+            CursorEncoding::UrlSafe => &BASE64_URL_SAFE,
+            CursorEncoding::UrlSafeNoPad => &BASE64_URL_SAFE_NO_PAD,
+            CursorEncoding::Standard => &BASE64_STANDARD,
+            CursorEncoding::StandardNoPad => &BASE64_STANDARD_NO_PAD,
+        }
+    }
+}
+
+/// How cursors are encoded: `base64(version || json({"k": [k1, k2, ...], "s": sort}))`,
+/// with the JSON optionally followed by an HMAC-SHA256 of it. With an encryption key
+/// the (signed) JSON is encrypted with ChaCha20-Poly1305 as `nonce || ciphertext`.
+/// Another [`CursorCodec`] replaces the JSON and the version.
+///
+/// Cursors from before the version byte are a bare JSON array and still decode as
+/// version 1.
+#[derive(Clone, Default)]
+pub struct CursorConfig {
+    pub encoding: CursorEncoding,
+    /// The sort the cursors are issued under (e.g. `created_at desc,id desc`). A
+    /// cursor carrying a different sort is rejected, since its boundary would be
+    /// compared in the wrong direction, or on other keys when it's replayed against
+    /// a paginator configured with other keys. Cursors without one (version 1, or
+    /// from [`encode_cursor`]) are rejected too, unless `accept_unsorted`.
+    pub sort: Option<String>,
+    /// Accept cursors without a sort under any sort.
+    pub accept_unsorted: bool,
+    pub signing_key: Option<Vec<u8>>,
+    /// The page size encoded into cursors, if any.
+    pub limit: Option<u32>,
+    #[cfg(feature = "crypto")]
+    pub encryption_key: Option<[u8; 32]>,
+    /// [`JsonCodec`] when not set.
+    pub codec: Option<Arc<dyn CursorCodec>>,
+}
+
+impl CursorConfig {
+    pub fn encode(&self, keys: Vec<Option<String>>) -> Result<String, PaginationError> {
+        let payload = CursorPayload {
+            keys,
+            sort: self.sort.clone(),
+            limit: self.limit,
+        };
+
+        let codec = self.codec();
+        let mut cursor_bytes = codec.encode(&payload)?;
+
+        if let Some(signing_key) = &self.signing_key {
+            let signature = sign(signing_key, &cursor_bytes)?.finalize().into_bytes();
+            cursor_bytes.extend_from_slice(&signature);
+        }
+
+        #[cfg(feature = "crypto")]
+        if let Some(encryption_key) = &self.encryption_key {
+            cursor_bytes = encrypt(encryption_key, &cursor_bytes)?;
+        }
+
+        cursor_bytes.insert(0, codec.version());
+
+        Ok(self.from_bytes(&cursor_bytes))
+    }
+
+    /// The payload of a cursor before base64, e.g. for a protobuf `bytes` field
+    /// that is base64 encoded by the transport already.
+    pub fn to_bytes(&self, cursor: &str) -> Result<Vec<u8>, PaginationError> {
+        let cursor = unescape(cursor.trim());
+        match self.encoding.engine().decode(&cursor) {
+            Ok(bytes) => Ok(bytes),
+            Err(_) => Err(PaginationError::InvalidCursor(CursorError::Malformed)),
+        }
+    }
+
+    /// The cursor of a payload from [`CursorConfig::to_bytes`].
+    pub fn from_bytes(&self, bytes: &[u8]) -> String {
+        self.encoding.engine().encode(bytes)
+    }
+
+    pub fn decode(
+        &self,
+        cursor: String,
+        len: usize,
+    ) -> Result<Vec<Option<String>>, PaginationError> {
+        self.decode_bytes(self.to_bytes(&cursor)?, len)
+    }
+
+    /// Like [`CursorConfig::decode`], for a payload from [`CursorConfig::to_bytes`].
+    pub fn decode_bytes(
+        &self,
+        bytes: Vec<u8>,
+        len: usize,
+    ) -> Result<Vec<Option<String>>, PaginationError> {
+        let values = self.decode_payload(bytes)?.keys;
+
+        if values.len() != len {
+            return Err(PaginationError::InvalidCursor(CursorError::WrongLength));
+        }
+
+        Ok(values)
+    }
+
+    /// Decodes the whole payload of `cursor`, under any sort, e.g. to continue with
+    /// the sort and limit of a self-describing cursor. The signature is still
+    /// checked.
+    pub fn decode_unsorted(&self, cursor: &str) -> Result<CursorPayload, PaginationError> {
+        let config = CursorConfig {
+            sort: None,
+            ..self.clone()
+        };
+        config.decode_payload(self.to_bytes(cursor)?)
+    }
+
+    fn decode_values(&self, cursor: String) -> Result<Vec<Option<String>>, PaginationError> {
+        Ok(self.decode_payload(self.to_bytes(&cursor)?)?.keys)
+    }
+
+    fn decode_payload(&self, mut bytes: Vec<u8>) -> Result<CursorPayload, PaginationError> {
+        let codec = self.codec();
+        let version = match bytes.first() {
+            Some(&version)
+                if version == codec.version()
+                    || version == CURSOR_VERSION
+                    || version == CURSOR_VERSION_1 =>
+            {
+                bytes.remove(0);
+                version
+            }
+            // unversioned cursors are bare JSON arrays
+            Some(b'[') => CURSOR_VERSION_1,
+            Some(_) => {
+                return Err(PaginationError::InvalidCursor(
+                    CursorError::UnsupportedVersion,
+                ));
+            }
+            None => {
+                return Err(PaginationError::InvalidCursor(CursorError::Empty));
+            }
+        };
+
+        #[cfg(feature = "crypto")]
+        if let Some(encryption_key) = &self.encryption_key {
+            bytes = decrypt(encryption_key, &bytes)?;
+        }
+
+        if let Some(signing_key) = &self.signing_key {
+            if bytes.len() < SIGNATURE_LEN {
+                return Err(PaginationError::InvalidCursor(CursorError::Tampered));
+            }
+
+            let signature = bytes.split_off(bytes.len() - SIGNATURE_LEN);
+            // verify_slice compares in constant time
+            if sign(signing_key, &bytes)?.verify_slice(&signature).is_err() {
+                return Err(PaginationError::InvalidCursor(CursorError::Tampered));
+            }
+        }
+
+        let payload = if version == codec.version() {
+            codec.decode(&bytes)?
+        } else if version == CURSOR_VERSION {
+            JsonCodec.decode(&bytes)?
+        } else {
+            match serde_json::from_slice(&bytes) {
+                Ok(keys) => CursorPayload {
+                    keys,
+                    sort: None,
+                    limit: None,
+                },
+                Err(_) => {
+                    return Err(PaginationError::InvalidCursor(CursorError::InvalidPayload));
+                }
+            }
+        };
+
+        if let Some(expected) = &self.sort {
+            match &payload.sort {
+                Some(sort) if sort_keys(sort) != sort_keys(expected) => {
+                    return Err(PaginationError::InvalidCursor(CursorError::KeysMismatch));
+                }
+                Some(sort) if sort != expected => {
+                    return Err(PaginationError::InvalidCursor(CursorError::SortMismatch));
+                }
+                // nothing tells which keys or directions a cursor without a sort is for
+                None if !self.accept_unsorted => {
+                    return Err(PaginationError::InvalidCursor(CursorError::SortMismatch));
+                }
+                _ => {}
+            }
+        }
+
+        Ok(payload)
+    }
+
+    fn codec(&self) -> &dyn CursorCodec {
+        match &self.codec {
+            Some(codec) => codec.as_ref(),
+            None => &JsonCodec,
+        }
+    }
+}
+
+/// Checks that `cursor` is base64 of any [`CursorEncoding`], without decoding its
+/// payload, which may be signed or encrypted.
+pub(crate) fn check_format(cursor: &str) -> Result<(), PaginationError> {
+    let cursor = unescape(cursor.trim());
+    let encodings = [
+        CursorEncoding::UrlSafe,
+        CursorEncoding::UrlSafeNoPad,
+        CursorEncoding::Standard,
+        CursorEncoding::StandardNoPad,
+    ];
+
+    match encodings
+        .iter()
+        .find_map(|encoding| encoding.engine().decode(&cursor).ok())
+    {
+        Some(bytes) if bytes.is_empty() => Err(PaginationError::InvalidCursor(CursorError::Empty)),
+        Some(_) => Ok(()),
+        None => Err(PaginationError::InvalidCursor(CursorError::Malformed)),
+    }
+}
+
+/// Undoes one pass of percent-encoding, e.g. of the `=` padding by a proxy encoding
+/// the query string again, when every `%` starts an escape of an ASCII character.
+/// Base64 has no `%`, so anything else is left for the base64 decoding to reject.
+fn unescape(cursor: &str) -> String {
+    if !cursor.contains('%') {
+        return cursor.to_string();
+    }
+
+    let mut unescaped = String::with_capacity(cursor.len());
+    let mut chars = cursor.chars();
+    while let Some(c) = chars.next() {
+        if c != '%' {
+            unescaped.push(c);
+            continue;
+        }
+
+        let escape: String = chars.by_ref().take(2).collect();
+        if escape.len() != 2 || !escape.chars().all(|c| c.is_ascii_hexdigit()) {
+            return cursor.to_string();
+        }
+        match u8::from_str_radix(&escape, 16) {
+            Ok(byte) if byte.is_ascii() => unescaped.push(byte as char),
+            _ => return cursor.to_string(),
+        }
+    }
+    unescaped
+}
+
+/// The key names of a sort like `created_at desc,id desc`.
+fn sort_keys(sort: &str) -> Vec<&str> {
+    sort.split(',')
+        .map(|column| column.split_once(' ').map_or(column, |(key, _)| key))
+        .collect()
+}
+
+fn sign(signing_key: &[u8], payload: &[u8]) -> Result<HmacSha256, PaginationError> {
+    let mut mac = match HmacSha256::new_from_slice(signing_key) {
+        Ok(mac) => mac,
+        Err(err) => {
+            return Err(PaginationError::Configuration(format!(
+                "failed to create cursor signature: {}",
+                err
+            )));
+        }
+    };
+
+    mac.update(payload);
+    Ok(mac)
+}
+
+#[cfg(feature = "crypto")]
+fn encrypt(encryption_key: &[u8; 32], payload: &[u8]) -> Result<Vec<u8>, PaginationError> {
+    let cipher = ChaCha20Poly1305::new(Key::from_slice(encryption_key));
+    let nonce = ChaCha20Poly1305::generate_nonce(&mut OsRng);
+
+    let ciphertext = match cipher.encrypt(&nonce, payload) {
+        Ok(ciphertext) => ciphertext,
+        Err(err) => {
+            return Err(PaginationError::Serialization(format!(
+                "failed to encrypt cursor: {}",
+                err
+            )));
+        }
+    };
+
+    let mut bytes = nonce.to_vec();
+    bytes.extend_from_slice(&ciphertext);
+    Ok(bytes)
+}
+
+#[cfg(feature = "crypto")]
+fn decrypt(encryption_key: &[u8; 32], bytes: &[u8]) -> Result<Vec<u8>, PaginationError> {
+    if bytes.len() < NONCE_LEN {
+        return Err(PaginationError::InvalidCursor(CursorError::Tampered));
+    }
+
+    let cipher = ChaCha20Poly1305::new(Key::from_slice(encryption_key));
+    let (nonce, ciphertext) = bytes.split_at(NONCE_LEN);
+
+    match cipher.decrypt(Nonce::from_slice(nonce), ciphertext) {
+        Ok(payload) => Ok(payload),
+        Err(_) => Err(PaginationError::InvalidCursor(CursorError::Tampered)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn signed(signing_key: &[u8]) -> CursorConfig {
+        CursorConfig {
+            signing_key: Some(signing_key.to_vec()),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn single_key() {
+        let cursor = CursorConfig::default()
+            .encode(vec![Some(String::from("42"))])
+            .unwrap();
+
+        let values = CursorConfig::default().decode(cursor.clone(), 1).unwrap();
+        assert_eq!(values, vec![Some(String::from("42"))]);
+
+        assert!(CursorConfig::default().decode(cursor, 2).is_err());
+    }
+
+    #[test]
+    fn raw_bytes() {
+        let config = CursorConfig {
+            signing_key: Some(b"secret".to_vec()),
+            sort: Some(String::from("created_at desc,id desc")),
+            ..Default::default()
+        };
+        let keys = vec![Some(String::from("2024-01-01T00:00:00Z")), None];
+        let cursor = config.encode(keys.clone()).unwrap();
+
+        let bytes = config.to_bytes(&cursor).unwrap();
+        assert_eq!(bytes[0], CURSOR_VERSION);
+        assert_eq!(config.decode_bytes(bytes.clone(), 2).unwrap(), keys);
+        assert_eq!(config.decode(cursor.clone(), 2).unwrap(), keys);
+        assert_eq!(config.from_bytes(&bytes), cursor);
+
+        assert!(config.decode_bytes(bytes[1..].to_vec(), 2).is_err());
+        assert!(config.to_bytes("not a cursor").is_err());
+    }
+
+    #[test]
+    fn public_helpers() {
+        let keys = vec![String::from("2024-01-01T00:00:00Z"), String::from("7")];
+
+        let cursor = encode_cursor(&keys);
+        assert_eq!(decode_cursor(&cursor).unwrap(), keys);
+
+        // the paginator decodes the same cursors
+        let values = CursorConfig::default().decode(cursor, 2).unwrap();
+        assert_eq!(values, keys.into_iter().map(Some).collect::<Vec<_>>());
+
+        assert!(decode_cursor("not a cursor").is_err());
+
+        // a NULL value is reported as such, not as tampering
+        let cursor = CursorConfig::default()
+            .encode(vec![None, Some(String::from("7"))])
+            .unwrap();
+        assert!(matches!(
+            decode_cursor(&cursor),
+            Err(PaginationError::InvalidCursor(CursorError::NullKey))
+        ));
+    }
+
+    #[test]
+    fn standard_encoding() {
+        let config = CursorConfig {
+            encoding: CursorEncoding::Standard,
+            ..Default::default()
+        };
+        // "???>>>" encodes to '/' and '+' here where the URL-safe alphabet has '_' and '-'
+        let keys = vec![Some(String::from("???>>>")), Some(String::from("78"))];
+
+        let cursor = config.encode(keys.clone()).unwrap();
+        assert!(cursor.contains('/') && cursor.contains('+'));
+        assert!(cursor.ends_with('='));
+        assert_eq!(BASE64_STANDARD.decode(&cursor).unwrap()[0], CURSOR_VERSION);
+
+        assert_eq!(config.decode(cursor, 2).unwrap(), keys);
+    }
+
+    #[test]
+    fn versions() {
+        let cursor = CursorConfig::default()
+            .encode(vec![Some(String::from("42"))])
+            .unwrap();
+        let bytes = BASE64_URL_SAFE.decode(&cursor).unwrap();
+        assert_eq!(bytes[0], CURSOR_VERSION);
+
+        // unversioned cursors decode as version 1
+        let legacy = BASE64_URL_SAFE.encode(serde_json::to_vec(&vec!["42", "7"]).unwrap());
+        let values = CursorConfig::default().decode(legacy, 2).unwrap();
+        assert_eq!(
+            values,
+            vec![Some(String::from("42")), Some(String::from("7"))]
+        );
+
+        let mut version_1 = vec![CURSOR_VERSION_1];
+        version_1.extend(serde_json::to_vec(&vec!["42"]).unwrap());
+        let version_1 = BASE64_URL_SAFE.encode(&version_1);
+        let values = CursorConfig::default().decode(version_1, 1).unwrap();
+        assert_eq!(values, vec![Some(String::from("42"))]);
+
+        let mut unknown = bytes.clone();
+        unknown[0] = 9;
+        let unknown = BASE64_URL_SAFE.encode(&unknown);
+        assert_eq!(decode_err(unknown, 1), CursorError::UnsupportedVersion);
+    }
+
+    fn decode_err(cursor: String, len: usize) -> CursorError {
+        match CursorConfig::default().decode(cursor, len) {
+            Err(PaginationError::InvalidCursor(message)) => message,
+            _ => panic!("cursor decoded"),
+        }
+    }
+
+    #[test]
+    fn decodes_mangled_cursor() {
+        let config = CursorConfig::default();
+        let keys = vec![
+            Some(String::from("2024-01-02T03:04:05Z")),
+            Some(String::from("7")),
+        ];
+        let cursor = config.encode(keys.clone()).unwrap();
+        assert!(cursor.ends_with('='));
+
+        assert_eq!(config.decode(format!("{}\n", cursor), 2).unwrap(), keys);
+        assert_eq!(config.decode(format!("  {} ", cursor), 2).unwrap(), keys);
+        let escaped = cursor.replace('=', "%3D");
+        assert_eq!(config.decode(escaped.clone(), 2).unwrap(), keys);
+
+        // only a single pass, and no malformed escapes
+        assert_eq!(
+            decode_err(escaped.replace('%', "%25"), 2),
+            CursorError::Malformed
+        );
+        assert_eq!(
+            decode_err(format!("{}%3", cursor), 2),
+            CursorError::Malformed
+        );
+        assert_eq!(
+            decode_err(format!("{}%E2%82%AC", cursor), 2),
+            CursorError::Malformed
+        );
+    }
+
+    #[test]
+    fn errors() {
+        assert_eq!(
+            decode_err(String::from("not base64!"), 2),
+            CursorError::Malformed
+        );
+        assert_eq!(decode_err(String::new(), 2), CursorError::Empty);
+
+        // numbers rather than strings
+        let mut numbers = vec![CURSOR_VERSION_1];
+        numbers.extend(serde_json::to_vec(&vec![123, 456]).unwrap());
+        assert_eq!(
+            decode_err(BASE64_URL_SAFE.encode(&numbers), 2),
+            CursorError::InvalidPayload
+        );
+        let legacy = BASE64_URL_SAFE.encode(serde_json::to_vec(&vec![123, 456]).unwrap());
+        assert_eq!(decode_err(legacy, 2), CursorError::InvalidPayload);
+
+        let mut object = vec![CURSOR_VERSION];
+        object.extend(br#"{"k":"a"}"#);
+        assert_eq!(
+            decode_err(BASE64_URL_SAFE.encode(&object), 1),
+            CursorError::InvalidPayload
+        );
+
+        let cursor = encode_cursor(&[String::from("a"), String::from("b"), String::from("c")]);
+        assert_eq!(decode_err(cursor, 2), CursorError::WrongLength);
+    }
+
+    fn sorted(sort: &str) -> CursorConfig {
+        CursorConfig {
+            sort: Some(sort.to_string()),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn sort_mismatch() {
+        let keys = vec![Some(String::from("7"))];
+        let cursor = sorted("id asc").encode(keys.clone()).unwrap();
+
+        assert_eq!(sorted("id asc").decode(cursor.clone(), 1).unwrap(), keys);
+        assert!(matches!(
+            sorted("id desc").decode(cursor.clone(), 1),
+            Err(PaginationError::InvalidCursor(CursorError::SortMismatch))
+        ));
+        assert!(matches!(
+            sorted("name asc").decode(cursor, 1),
+            Err(PaginationError::InvalidCursor(CursorError::KeysMismatch))
+        ));
+
+        // cursors built without a sort are only accepted as legacy cursors
+        let cursor = encode_cursor(&[String::from("7")]);
+        assert!(matches!(
+            sorted("id desc").decode(cursor.clone(), 1),
+            Err(PaginationError::InvalidCursor(CursorError::SortMismatch))
+        ));
+        let unversioned = BASE64_URL_SAFE.encode(serde_json::to_vec(&keys).unwrap());
+        assert!(matches!(
+            sorted("id desc").decode(unversioned.clone(), 1),
+            Err(PaginationError::InvalidCursor(CursorError::SortMismatch))
+        ));
+        let legacy = CursorConfig {
+            accept_unsorted: true,
+            ..sorted("id desc")
+        };
+        assert_eq!(legacy.decode(cursor, 1).unwrap(), keys);
+        assert_eq!(legacy.decode(unversioned, 1).unwrap(), keys);
+    }
+
+    #[test]
+    fn limit_in_payload() {
+        let mut config = sorted("id asc");
+        config.limit = Some(20);
+        let cursor = config.encode(vec![Some(String::from("7"))]).unwrap();
+
+        let payload = sorted("id desc").decode_unsorted(&cursor).unwrap();
+        assert_eq!(payload.limit, Some(20));
+        assert_eq!(payload.sort.as_deref(), Some("id asc"));
+
+        let bytes = BASE64_URL_SAFE.decode(&cursor).unwrap();
+        assert_eq!(&bytes[1..], br#"{"k":["7"],"s":"id asc","l":20}"#);
+
+        #[cfg(feature = "msgpack")]
+        for limit in [Some(20), None] {
+            let config = CursorConfig {
+                limit,
+                codec: Some(Arc::new(MessagePackCodec)),
+                ..sorted("id asc")
+            };
+            let cursor = config.encode(vec![Some(String::from("7"))]).unwrap();
+            assert_eq!(config.decode_unsorted(&cursor).unwrap().limit, limit);
+        }
+    }
+
+    #[cfg(feature = "msgpack")]
+    #[test]
+    fn message_pack_codec() {
+        let config = CursorConfig {
+            sort: Some(String::from("created_at desc,id desc")),
+            codec: Some(Arc::new(MessagePackCodec)),
+            ..Default::default()
+        };
+        let keys = vec![Some(String::from("2024-01-01T00:00:00Z")), None];
+
+        let cursor = config.encode(keys.clone()).unwrap();
+        assert_eq!(BASE64_URL_SAFE.decode(&cursor).unwrap()[0], 3);
+        assert_eq!(config.decode(cursor.clone(), 2).unwrap(), keys);
+
+        let json = CursorConfig {
+            codec: None,
+            ..config.clone()
+        };
+        let json_cursor = json.encode(keys.clone()).unwrap();
+        assert!(cursor.len() < json_cursor.len());
+
+        // JSON cursors handed out before switching codecs still decode
+        assert_eq!(config.decode(json_cursor, 2).unwrap(), keys);
+        // but a MessagePack cursor doesn't decode without its codec
+        assert!(json.decode(cursor, 2).is_err());
+    }
+
+    #[test]
+    fn signed_round_trip() {
+        let config = signed(b"secret");
+        let keys = vec![
+            Some(String::from("2024-01-01T00:00:00Z")),
+            Some(String::from("7")),
+        ];
+
+        let cursor = config.encode(keys.clone()).unwrap();
+        assert_eq!(config.decode(cursor, 2).unwrap(), keys);
+    }
+
+    #[test]
+    fn signed_rejects_tampering() {
+        let config = signed(b"secret");
+        let cursor = config.encode(vec![Some(String::from("7"))]).unwrap();
+
+        let mut bytes = BASE64_URL_SAFE.decode(&cursor).unwrap();
+        bytes[3] = b'8';
+        let tampered = BASE64_URL_SAFE.encode(&bytes);
+        assert!(matches!(
+            config.decode(tampered, 1),
+            Err(PaginationError::InvalidCursor(CursorError::Tampered))
+        ));
+
+        // a cursor signed with another key
+        let other = signed(b"other")
+            .encode(vec![Some(String::from("7"))])
+            .unwrap();
+        assert!(config.decode(other, 1).is_err());
+
+        // an unsigned cursor
+        let unsigned = CursorConfig::default()
+            .encode(vec![Some(String::from("7"))])
+            .unwrap();
+        assert!(config.decode(unsigned, 1).is_err());
+    }
+
+    #[cfg(feature = "crypto")]
+    fn encrypted(encryption_key: [u8; 32]) -> CursorConfig {
+        CursorConfig {
+            encryption_key: Some(encryption_key),
+            ..Default::default()
+        }
+    }
+
+    #[cfg(feature = "crypto")]
+    #[test]
+    fn encrypted_round_trip() {
+        let config = encrypted([7; 32]);
+        let keys = vec![Some(String::from("42"))];
+
+        let cursor = config.encode(keys.clone()).unwrap();
+        // the key values don't show through
+        let bytes = BASE64_URL_SAFE.decode(&cursor).unwrap();
+        assert!(serde_json::from_slice::<Vec<Option<String>>>(&bytes).is_err());
+
+        assert_eq!(config.decode(cursor, 1).unwrap(), keys);
+    }
+
+    #[cfg(feature = "crypto")]
+    #[test]
+    fn encrypted_rejects_other_key() {
+        let cursor = encrypted([7; 32])
+            .encode(vec![Some(String::from("42"))])
+            .unwrap();
+        assert!(encrypted([8; 32]).decode(cursor, 1).is_err());
+    }
+}
@@ -0,0 +1,171 @@
+use std::fmt;
+
+#[cfg(feature = "service-util")]
+use service_util::error;
+
+/// Why a cursor was rejected, e.g. to map to a localized message rather than
+/// [`CursorError::message`], the English one.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum CursorError {
+    /// Not base64 of the paginator's encoding.
+    Malformed,
+    /// Decodes to no bytes at all.
+    Empty,
+    /// Issued by a newer version, or with a codec the paginator doesn't use.
+    UnsupportedVersion,
+    /// The payload doesn't deserialize.
+    InvalidPayload,
+    /// The signature doesn't match, or it doesn't decrypt.
+    Tampered,
+    /// Issued for another number of keys.
+    WrongLength,
+    /// Issued for other keys.
+    KeysMismatch,
+    /// Issued for the same keys in another order.
+    SortMismatch,
+    /// NULL for a key that isn't nullable.
+    NullKey,
+    /// A key value doesn't parse as its type, with the message of
+    /// [`crate::KeyParse::parse_key`].
+    InvalidKey(&'static str),
+}
+
+impl CursorError {
+    pub fn message(&self) -> &'static str {
+        match self {
+            CursorError::Malformed => "cursor is not valid base64",
+            CursorError::Empty => "cursor is empty",
+            CursorError::UnsupportedVersion => "unsupported cursor version",
+            CursorError::InvalidPayload => "cursor payload is not a list of keys",
+            CursorError::Tampered => "invalid cursor",
+            CursorError::WrongLength => "cursor has the wrong number of keys",
+            CursorError::KeysMismatch => "cursor keys mismatch",
+            CursorError::SortMismatch => "cursor sort mismatch",
+            CursorError::NullKey => "cursor key is NULL",
+            CursorError::InvalidKey(message) => message,
+        }
+    }
+}
+
+impl fmt::Display for CursorError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.message())
+    }
+}
+
+/// Why pagination failed.
+///
+/// With the `service-util` feature this converts into `service_util::error::Error`,
+/// as an invalid argument for the variants caused by the request and as an internal
+/// error (logged) for the others.
+#[derive(Debug)]
+pub enum PaginationError {
+    /// The request's cursor doesn't decode, or doesn't fit the keys it's used with.
+    InvalidCursor(CursorError),
+    /// The request is invalid otherwise, e.g. an unknown `sort_by`.
+    InvalidArgument(&'static str),
+    /// Every reason [`crate::PaginationRequest::validate`] rejected the request for.
+    InvalidRequest(Vec<&'static str>),
+    /// The database failed to run a query.
+    Database(sqlx::Error),
+    /// A cursor couldn't be built.
+    Serialization(String),
+    /// [`crate::Paginator::paginate_all`] read more than this many rows.
+    TooManyRows(usize),
+    /// The paginator is set up wrong, e.g. paginated with another number of keys
+    /// than it was configured with.
+    Configuration(String),
+}
+
+impl PaginationError {
+    /// Whether running the same pagination again may succeed, e.g. to retry it
+    /// with a backoff: the connection to the database failed, the pool timed out
+    /// waiting for a connection, the transaction lost a serialization conflict or
+    /// a deadlock, or the database was locked (SQLite's `SQLITE_BUSY` and
+    /// `SQLITE_LOCKED`, MySQL's lock wait timeout). Errors of the query itself,
+    /// e.g. a column that doesn't exist, fail again the same way.
+    pub fn is_retryable(&self) -> bool {
+        let PaginationError::Database(err) = self else {
+            return false;
+        };
+
+        match err {
+            sqlx::Error::Io(_) | sqlx::Error::PoolTimedOut | sqlx::Error::WorkerCrashed => true,
+            sqlx::Error::Database(err) => {
+                #[cfg(feature = "sqlite")]
+                if err
+                    .try_downcast_ref::<sqlx::sqlite::SqliteError>()
+                    .is_some()
+                {
+                    // SQLITE_BUSY and SQLITE_LOCKED, with their extended codes
+                    return matches!(
+                        err.code().as_deref(),
+                        Some("5" | "6" | "261" | "262" | "517")
+                    );
+                }
+
+                #[cfg(feature = "mysql")]
+                if let Some(err) = err.try_downcast_ref::<sqlx::mysql::MySqlDatabaseError>() {
+                    // ER_LOCK_WAIT_TIMEOUT and ER_LOCK_DEADLOCK
+                    return matches!(err.number(), 1205 | 1213);
+                }
+
+                // SQLSTATE serialization_failure and Postgres' deadlock_detected
+                matches!(err.code().as_deref(), Some("40001" | "40P01"))
+            }
+            _ => false,
+        }
+    }
+}
+
+impl fmt::Display for PaginationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PaginationError::InvalidCursor(err) => write!(f, "{}", err),
+            PaginationError::InvalidArgument(message) => write!(f, "{}", message),
+            PaginationError::InvalidRequest(messages) => write!(f, "{}", messages.join(", ")),
+            PaginationError::Database(err) => write!(f, "failed to run pagination query: {}", err),
+            PaginationError::Serialization(message) => write!(f, "{}", message),
+            PaginationError::Configuration(message) => write!(f, "{}", message),
+            PaginationError::TooManyRows(max_rows) => {
+                write!(f, "more than {} rows to paginate", max_rows)
+            }
+        }
+    }
+}
+
+impl std::error::Error for PaginationError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            PaginationError::Database(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<sqlx::Error> for PaginationError {
+    fn from(err: sqlx::Error) -> Self {
+        PaginationError::Database(err)
+    }
+}
+
+#[cfg(feature = "service-util")]
+impl From<PaginationError> for error::Error {
+    fn from(err: PaginationError) -> Self {
+        match err {
+            PaginationError::InvalidCursor(err) => {
+                error::invalid_argument_with_message(err.message())
+            }
+            PaginationError::InvalidArgument(message) => {
+                error::invalid_argument_with_message(message)
+            }
+            PaginationError::InvalidRequest(messages) => {
+                error::invalid_argument_with_message(&messages.join(", "))
+            }
+            err => {
+                log::error!("{}", err);
+                error::internal()
+            }
+        }
+    }
+}
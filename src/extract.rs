@@ -0,0 +1,79 @@
+use std::ops::Deref;
+
+use axum::{extract::FromRequestParts, extract::Query, http::request::Parts};
+use service_util::error;
+
+use crate::{PaginationError, PaginationRequest};
+
+/// Extracts a [`PaginationRequest`] from the query string, rejecting it with an
+/// invalid argument error when it doesn't deserialize (e.g. an unknown
+/// `sort_order`) or doesn't pass [`PaginationRequest::validate`] with `MAX_LIMIT`.
+///
+/// `MAX_LIMIT` defaults to the default of [`crate::Paginator::max_limit`], and
+/// should be set to the same value as the paginator's.
+#[derive(Clone, Debug)]
+pub struct Pagination<const MAX_LIMIT: u32 = 100>(pub PaginationRequest);
+
+impl<const MAX_LIMIT: u32> Deref for Pagination<MAX_LIMIT> {
+    type Target = PaginationRequest;
+
+    fn deref(&self) -> &PaginationRequest {
+        &self.0
+    }
+}
+
+impl<S: Send + Sync, const MAX_LIMIT: u32> FromRequestParts<S> for Pagination<MAX_LIMIT> {
+    type Rejection = error::Error;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        let request = match Query::<PaginationRequest>::try_from_uri(&parts.uri) {
+            Ok(Query(request)) => request,
+            Err(_) => {
+                return Err(
+                    PaginationError::InvalidArgument("invalid pagination parameters").into(),
+                );
+            }
+        };
+
+        request.validate(MAX_LIMIT)?;
+        Ok(Pagination(request))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::{body::Body, http::Request};
+
+    use super::*;
+    use crate::SortOrder;
+
+    async fn extract(uri: &str) -> Result<Pagination, error::Error> {
+        let (mut parts, _) = Request::builder()
+            .uri(uri)
+            .body(Body::empty())
+            .unwrap()
+            .into_parts();
+        Pagination::from_request_parts(&mut parts, &()).await
+    }
+
+    #[tokio::test]
+    async fn extracts_request() {
+        let pagination = extract("/users?cursor=abc&limit=20&sort_order=asc")
+            .await
+            .unwrap();
+
+        assert_eq!(pagination.cursor.as_deref(), Some("abc"));
+        assert_eq!(pagination.limit, Some(20));
+        assert!(pagination.sort_order == Some(SortOrder::Asc));
+
+        assert!(extract("/users").await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn rejects_invalid_request() {
+        assert!(extract("/users?sort_order=sideways").await.is_err());
+        assert!(extract("/users?limit=ten").await.is_err());
+        assert!(extract("/users?limit=101").await.is_err());
+        assert!(extract("/users?page=0").await.is_err());
+    }
+}
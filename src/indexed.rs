@@ -0,0 +1,13 @@
+use indexmap::IndexMap;
+use serde::Serialize;
+
+/// A page whose rows are keyed by the value of their first key, in the order of
+/// the page, returned by [`crate::Paginator::paginate_indexed`].
+#[derive(Clone, Debug, Serialize)]
+pub struct IndexedPage<T> {
+    pub data: IndexMap<String, T>,
+    pub next_cursor: Option<String>,
+    pub prev_cursor: Option<String>,
+    pub has_next_page: bool,
+    pub has_prev_page: bool,
+}
@@ -0,0 +1,486 @@
+use sqlx::{Database, QueryBuilder};
+
+use crate::NullsOrder;
+
+/// A column of the keyset, described in the order rows are walked.
+pub struct KeyColumn {
+    /// Column or SQL expression compared and ordered by.
+    pub name: String,
+    /// Name the base query selects an expression `name` as.
+    pub alias: Option<String>,
+    pub descending: bool,
+    pub nulls: Option<NullsOrder>,
+    /// Collation the column is compared and ordered under.
+    pub collation: Option<String>,
+}
+
+impl KeyColumn {
+    /// The column as compared and ordered by, with its collation.
+    fn collated(&self) -> String {
+        match &self.collation {
+            Some(collation) => format!("{} COLLATE \"{}\"", self.name, collation),
+            None => self.name.clone(),
+        }
+    }
+
+    /// Whether NULLs come after every other value in the walked order. Without an
+    /// explicit setting this follows the database, which sorts NULLs as larger than
+    /// any value (Postgres) or as smaller (`nulls_smallest`, MySQL and SQLite).
+    fn nulls_last(&self, nulls_smallest: bool) -> bool {
+        match self.nulls {
+            Some(NullsOrder::Last) => true,
+            Some(NullsOrder::First) => false,
+            None => self.descending == nulls_smallest,
+        }
+    }
+}
+
+/// Builds the keyset WHERE, ORDER BY and LIMIT clauses for a list of columns.
+pub struct Keyset {
+    columns: Vec<KeyColumn>,
+    inclusive: bool,
+    nulls_smallest: bool,
+}
+
+impl Keyset {
+    pub fn new(columns: Vec<KeyColumn>) -> Self {
+        Keyset {
+            columns,
+            inclusive: false,
+            nulls_smallest: false,
+        }
+    }
+
+    /// Returns the keyset for a database sorting NULLs before every other value
+    /// by default, see [`crate::Backend::NULLS_SMALLEST`].
+    pub fn nulls_smallest(self, nulls_smallest: bool) -> Self {
+        Keyset {
+            nulls_smallest,
+            ..self
+        }
+    }
+
+    /// Returns the keyset matching the cursor's row itself too, to start at a
+    /// known row rather than after it.
+    pub fn inclusive(self) -> Self {
+        Keyset {
+            inclusive: true,
+            ..self
+        }
+    }
+
+    /// Returns the keyset walking the exact opposite order, used to page backward.
+    pub fn reversed(self) -> Self {
+        let columns = self
+            .columns
+            .into_iter()
+            .map(|column| KeyColumn {
+                name: column.name,
+                alias: column.alias,
+                descending: !column.descending,
+                nulls: column.nulls.map(|nulls| match nulls {
+                    NullsOrder::First => NullsOrder::Last,
+                    NullsOrder::Last => NullsOrder::First,
+                }),
+                collation: column.collation,
+            })
+            .collect();
+
+        Keyset { columns, ..self }
+    }
+
+    /// Returns the keyset with expressions replaced by their aliases and the table
+    /// qualifiers dropped from the column names, to order the rows of a subquery
+    /// selecting them.
+    pub fn unqualified(self) -> Self {
+        let columns = self
+            .columns
+            .into_iter()
+            .map(|column| {
+                let name = column.alias.unwrap_or(column.name);
+                KeyColumn {
+                    name: match name.rsplit_once('.') {
+                        Some((_, name)) => name.to_string(),
+                        None => name,
+                    },
+                    alias: None,
+                    ..column
+                }
+            })
+            .collect();
+
+        Keyset { columns, ..self }
+    }
+
+    /// Pushes a predicate matching the rows strictly after the cursor, or at or
+    /// after it when [`Keyset::inclusive`].
+    ///
+    /// `nulls` tells for every column whether the cursor value is NULL, and `bind`
+    /// pushes the bind of the cursor value at the given index.
+    ///
+    /// When every column is walked in the same direction and NULLs don't need to
+    /// be accounted for, this is a row comparison `(c1, c2) > ($1, $2)`, otherwise
+    /// it's expanded to `c1 > $1 OR (c1 = $1 AND c2 > $2)`.
+    pub fn push_where<'a, DB: Database>(
+        &self,
+        query: &mut QueryBuilder<'a, DB>,
+        nulls: &[bool],
+        bind: &mut dyn FnMut(&mut QueryBuilder<'a, DB>, usize),
+    ) {
+        let descending = self.columns.first().is_some_and(|column| column.descending);
+        let uniform = self
+            .columns
+            .iter()
+            .all(|column| column.descending == descending && column.nulls.is_none())
+            && !nulls.iter().any(|null| *null);
+
+        if uniform {
+            let names: Vec<String> = self.columns.iter().map(KeyColumn::collated).collect();
+            query.push(format!(" ({}) ", names.join(", ")));
+            query.push(match (descending, self.inclusive) {
+                (true, false) => "<",
+                (true, true) => "<=",
+                (false, false) => ">",
+                (false, true) => ">=",
+            });
+            query.push(" (");
+            for index in 0..self.columns.len() {
+                if index > 0 {
+                    query.push(", ");
+                }
+                bind(query, index);
+            }
+            query.push(")");
+            return;
+        }
+
+        query.push(" (");
+        for (index, null) in nulls.iter().enumerate() {
+            if index == 0 {
+                self.push_after(query, index, *null, bind);
+                continue;
+            }
+
+            query.push(" OR (");
+            for (previous, previous_null) in nulls[..index].iter().enumerate() {
+                self.push_equal(query, previous, *previous_null, bind);
+                query.push(" AND ");
+            }
+            self.push_after(query, index, *null, bind);
+            query.push(")");
+        }
+        query.push(")");
+    }
+
+    fn push_equal<'a, DB: Database>(
+        &self,
+        query: &mut QueryBuilder<'a, DB>,
+        index: usize,
+        null: bool,
+        bind: &mut dyn FnMut(&mut QueryBuilder<'a, DB>, usize),
+    ) {
+        let column = &self.columns[index];
+
+        if null {
+            query.push(format!("{} IS NULL", column.name));
+        } else {
+            query.push(format!("{} = ", column.collated()));
+            bind(query, index);
+        }
+    }
+
+    fn push_after<'a, DB: Database>(
+        &self,
+        query: &mut QueryBuilder<'a, DB>,
+        index: usize,
+        null: bool,
+        bind: &mut dyn FnMut(&mut QueryBuilder<'a, DB>, usize),
+    ) {
+        let column = &self.columns[index];
+        // only the last column decides whether the cursor's row itself matches
+        let inclusive = self.inclusive && index == self.columns.len() - 1;
+        let op = match (column.descending, inclusive) {
+            (true, false) => "<",
+            (true, true) => "<=",
+            (false, false) => ">",
+            (false, true) => ">=",
+        };
+
+        if null {
+            // nothing sorts after a trailing NULL, every value sorts after a leading one
+            match (column.nulls_last(self.nulls_smallest), inclusive) {
+                (true, false) => query.push("FALSE"),
+                (true, true) => query.push(format!("{} IS NULL", column.name)),
+                (false, false) => query.push(format!("{} IS NOT NULL", column.name)),
+                (false, true) => query.push("TRUE"),
+            };
+            return;
+        }
+
+        if column.nulls.is_some() && column.nulls_last(self.nulls_smallest) {
+            query.push(format!("({} {} ", column.collated(), op));
+            bind(query, index);
+            query.push(format!(" OR {} IS NULL)", column.name));
+        } else {
+            query.push(format!("{} {} ", column.collated(), op));
+            bind(query, index);
+        }
+    }
+
+    pub fn push_order_by<DB: Database>(&self, query: &mut QueryBuilder<'_, DB>) {
+        let columns: Vec<String> = self
+            .columns
+            .iter()
+            .map(|column| {
+                let mut order = format!(
+                    "{} {}",
+                    column.collated(),
+                    if column.descending { "DESC" } else { "ASC" }
+                );
+                match column.nulls {
+                    Some(NullsOrder::First) => order.push_str(" NULLS FIRST"),
+                    Some(NullsOrder::Last) => order.push_str(" NULLS LAST"),
+                    None => {}
+                }
+                order
+            })
+            .collect();
+
+        query.push(format!(" ORDER BY {}", columns.join(", ")));
+    }
+
+    pub fn push_limit<DB: Database>(&self, query: &mut QueryBuilder<'_, DB>, limit: u32) {
+        query.push(format!(" LIMIT {}", limit));
+    }
+
+    pub fn push_offset<DB: Database>(&self, query: &mut QueryBuilder<'_, DB>, offset: u64) {
+        query.push(format!(" OFFSET {}", offset));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use sqlx::Postgres;
+
+    use super::*;
+
+    fn keyset(columns: &[(&str, bool, Option<NullsOrder>)]) -> Keyset {
+        Keyset::new(
+            columns
+                .iter()
+                .map(|(name, descending, nulls)| KeyColumn {
+                    name: name.to_string(),
+                    alias: None,
+                    descending: *descending,
+                    nulls: nulls.clone(),
+                    collation: None,
+                })
+                .collect(),
+        )
+    }
+
+    fn push_where(keyset: &Keyset, nulls: &[bool]) -> String {
+        let mut query: QueryBuilder<Postgres> = QueryBuilder::new("SELECT * FROM t WHERE TRUE AND");
+        keyset.push_where(&mut query, nulls, &mut |query, index| {
+            query.push_bind(index as i64);
+        });
+        query.sql().to_string()
+    }
+
+    #[test]
+    fn row_comparison() {
+        let keyset = keyset(&[("created_at", true, None), ("id", true, None)]);
+        assert_eq!(
+            push_where(&keyset, &[false, false]),
+            "SELECT * FROM t WHERE TRUE AND (created_at, id) < ($1, $2)"
+        );
+
+        let mut query: QueryBuilder<Postgres> = QueryBuilder::new("");
+        keyset.push_order_by(&mut query);
+        keyset.push_limit(&mut query, 11);
+        keyset.push_offset(&mut query, 60);
+        assert_eq!(
+            query.sql(),
+            " ORDER BY created_at DESC, id DESC LIMIT 11 OFFSET 60"
+        );
+    }
+
+    #[test]
+    fn strict_on_every_column() {
+        // the cursor's row fails the last column's comparison, so it's never matched
+        let keyset = keyset(&[
+            ("created_at", false, Some(NullsOrder::Last)),
+            ("id", false, None),
+        ]);
+        assert_eq!(
+            push_where(&keyset, &[false, false]),
+            "SELECT * FROM t WHERE TRUE AND ((created_at > $1 OR created_at IS NULL) \
+             OR (created_at = $2 AND id > $3))"
+        );
+
+        let keyset = keyset.reversed();
+        assert_eq!(
+            push_where(&keyset, &[false, false]),
+            "SELECT * FROM t WHERE TRUE AND (created_at < $1 OR (created_at = $2 AND id < $3))"
+        );
+    }
+
+    #[test]
+    fn mixed_directions() {
+        let keyset = keyset(&[("priority", false, None), ("created_at", true, None)]);
+        assert_eq!(
+            push_where(&keyset, &[false, false]),
+            "SELECT * FROM t WHERE TRUE AND (priority > $1 OR (priority = $2 AND created_at < $3))"
+        );
+    }
+
+    #[test]
+    fn inclusive() {
+        let keyset = keyset(&[("created_at", true, None), ("id", true, None)]).inclusive();
+        assert_eq!(
+            push_where(&keyset, &[false, false]),
+            "SELECT * FROM t WHERE TRUE AND (created_at, id) <= ($1, $2)"
+        );
+
+        let keyset = keyset.reversed();
+        assert_eq!(
+            push_where(&keyset, &[false, false]),
+            "SELECT * FROM t WHERE TRUE AND (created_at, id) >= ($1, $2)"
+        );
+
+        // only the last column matches the cursor's value itself
+        let keyset = self::keyset(&[("priority", false, None), ("id", true, None)]).inclusive();
+        assert_eq!(
+            push_where(&keyset, &[false, false]),
+            "SELECT * FROM t WHERE TRUE AND (priority > $1 OR (priority = $2 AND id <= $3))"
+        );
+    }
+
+    #[test]
+    fn nulls_last() {
+        let keyset = keyset(&[
+            ("archived_at", false, Some(NullsOrder::Last)),
+            ("id", false, Some(NullsOrder::Last)),
+        ]);
+
+        assert_eq!(
+            push_where(&keyset, &[false, false]),
+            "SELECT * FROM t WHERE TRUE AND ((archived_at > $1 OR archived_at IS NULL) \
+             OR (archived_at = $2 AND (id > $3 OR id IS NULL)))"
+        );
+
+        // once the cursor reached the NULLs, only the tie-breaker moves forward
+        assert_eq!(
+            push_where(&keyset, &[true, false]),
+            "SELECT * FROM t WHERE TRUE AND (FALSE OR (archived_at IS NULL AND (id > $1 OR id IS NULL)))"
+        );
+
+        let mut query: QueryBuilder<Postgres> = QueryBuilder::new("");
+        keyset.push_order_by(&mut query);
+        assert_eq!(
+            query.sql(),
+            " ORDER BY archived_at ASC NULLS LAST, id ASC NULLS LAST"
+        );
+    }
+
+    #[test]
+    fn default_nulls_order() {
+        let keyset = keyset(&[("archived_at", false, None), ("id", false, None)]);
+
+        // Postgres sorts the NULLs last ascending, nothing follows them
+        assert_eq!(
+            push_where(&keyset, &[true, false]),
+            "SELECT * FROM t WHERE TRUE AND (FALSE OR (archived_at IS NULL AND id > $1))"
+        );
+
+        // MySQL and SQLite sort them first, every value follows them
+        let keyset = keyset.nulls_smallest(true);
+        assert_eq!(
+            push_where(&keyset, &[true, false]),
+            "SELECT * FROM t WHERE TRUE AND (archived_at IS NOT NULL \
+             OR (archived_at IS NULL AND id > $1))"
+        );
+        assert_eq!(
+            push_where(&keyset.reversed(), &[true, false]),
+            "SELECT * FROM t WHERE TRUE AND (FALSE OR (archived_at IS NULL AND id < $1))"
+        );
+    }
+
+    #[test]
+    fn nulls_first_reversed() {
+        let keyset = keyset(&[
+            ("archived_at", false, Some(NullsOrder::First)),
+            ("id", false, None),
+        ])
+        .reversed();
+
+        // walking backward over NULLS FIRST puts the NULLs last
+        assert_eq!(
+            push_where(&keyset, &[false, false]),
+            "SELECT * FROM t WHERE TRUE AND ((archived_at < $1 OR archived_at IS NULL) \
+             OR (archived_at = $2 AND id < $3))"
+        );
+
+        let mut query: QueryBuilder<Postgres> = QueryBuilder::new("");
+        keyset.push_order_by(&mut query);
+        assert_eq!(
+            query.sql(),
+            " ORDER BY archived_at DESC NULLS LAST, id DESC"
+        );
+    }
+
+    #[test]
+    fn unqualified() {
+        let keyset = keyset(&[
+            ("public.users.created_at", true, None),
+            ("users.id", true, None),
+        ])
+        .unqualified();
+
+        let mut query: QueryBuilder<Postgres> = QueryBuilder::new("");
+        keyset.push_order_by(&mut query);
+        assert_eq!(query.sql(), " ORDER BY created_at DESC, id DESC");
+
+        let keyset = Keyset::new(vec![KeyColumn {
+            name: String::from("lower(users.name)"),
+            alias: Some(String::from("sort_name")),
+            descending: false,
+            nulls: None,
+            collation: None,
+        }])
+        .unqualified();
+
+        let mut query: QueryBuilder<Postgres> = QueryBuilder::new("");
+        keyset.push_order_by(&mut query);
+        assert_eq!(query.sql(), " ORDER BY sort_name ASC");
+    }
+
+    #[test]
+    fn collated() {
+        let mut columns = keyset(&[("name", false, None), ("id", false, None)]).columns;
+        columns[0].collation = Some(String::from("und-x-icu"));
+        let keyset = Keyset::new(columns);
+        assert_eq!(
+            push_where(&keyset, &[false, false]),
+            "SELECT * FROM t WHERE TRUE AND (name COLLATE \"und-x-icu\", id) > ($1, $2)"
+        );
+
+        let mut query: QueryBuilder<Postgres> = QueryBuilder::new("");
+        keyset.push_order_by(&mut query);
+        assert_eq!(
+            query.sql(),
+            " ORDER BY name COLLATE \"und-x-icu\" ASC, id ASC"
+        );
+
+        // compared under the collation in the expanded form too, but checked for
+        // NULL as is
+        let mut columns = keyset.columns;
+        columns[0].nulls = Some(NullsOrder::Last);
+        let keyset = Keyset::new(columns);
+        assert_eq!(
+            push_where(&keyset, &[false, false]),
+            "SELECT * FROM t WHERE TRUE AND ((name COLLATE \"und-x-icu\" > $1 OR name IS NULL) \
+             OR (name COLLATE \"und-x-icu\" = $2 AND id > $3))"
+        );
+    }
+}
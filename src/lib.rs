@@ -1,203 +1,4028 @@
-use base64::{Engine, prelude::BASE64_URL_SAFE};
-use chrono::{DateTime, Utc};
-use serde::{Deserialize, Serialize};
-use service_util::error;
-use sqlx::{FromRow, Pool, Postgres, QueryBuilder, postgres::PgRow};
+use std::{fmt, marker::PhantomData, ops::Deref, str::FromStr, sync::Arc};
+
+#[cfg(feature = "chrono")]
+use chrono::{DateTime, FixedOffset, NaiveDateTime, SecondsFormat, Utc};
+use futures_util::{Stream, StreamExt, stream};
+use serde::{Deserialize, Deserializer, Serialize, de};
+use sqlx::{Acquire, ColumnIndex, Database, Decode, FromRow, Pool, Postgres, QueryBuilder, Type};
+#[cfg(feature = "utoipa")]
 use utoipa::{IntoParams, ToSchema};
 
-#[derive(Clone, Deserialize, Eq, PartialEq, ToSchema)]
+mod backend;
+mod cursor;
+mod error;
+#[cfg(feature = "axum")]
+mod extract;
+#[cfg(feature = "indexed")]
+mod indexed;
+mod keyset;
+#[cfg(feature = "jsonapi")]
+mod links;
+#[cfg(feature = "relay")]
+mod relay;
+
+pub use backend::Backend;
+use cursor::CursorConfig;
+#[cfg(feature = "msgpack")]
+pub use cursor::MessagePackCodec;
+pub use cursor::{
+    Cursor, CursorCodec, CursorEncoding, CursorPayload, JsonCodec, decode_cursor, encode_cursor,
+};
+pub use error::{CursorError, PaginationError};
+#[cfg(feature = "axum")]
+pub use extract::Pagination;
+#[cfg(feature = "indexed")]
+pub use indexed::IndexedPage;
+use keyset::{KeyColumn, Keyset};
+#[cfg(feature = "jsonapi")]
+pub use links::Links;
+#[cfg(feature = "relay")]
+pub use relay::{Connection, Edge, PageInfo};
+/// Derives [`KeyParse`] for a single-field tuple struct, e.g. `struct UserId(Uuid)`,
+/// delegating to the wrapped type. To bind such a key, also derive `sqlx::Type` with
+/// `#[sqlx(transparent)]`.
+#[cfg(feature = "derive")]
+pub use sqlx_cursor_paginator_derive::KeyParse;
+
+/// Serializes as `asc`/`desc`, and deserializes those case-insensitively along with
+/// `ascending`/`descending` and `+`/`-` (`%2B` in a query string, where `+` is a
+/// space).
+#[derive(Clone, Debug, Eq, PartialEq, Serialize)]
+#[cfg_attr(feature = "utoipa", derive(ToSchema))]
 #[serde(rename_all = "snake_case")]
 pub enum SortOrder {
     Asc,
     Desc,
 }
 
-#[derive(Clone, Default, Deserialize, IntoParams)]
-#[into_params(parameter_in = Query)]
+impl FromStr for SortOrder {
+    type Err = PaginationError;
+
+    fn from_str(value: &str) -> Result<SortOrder, PaginationError> {
+        match value.to_ascii_lowercase().as_str() {
+            "asc" | "ascending" | "+" => Ok(SortOrder::Asc),
+            "desc" | "descending" | "-" => Ok(SortOrder::Desc),
+            _ => Err(PaginationError::InvalidArgument(
+                "invalid sort_order, expected asc or desc",
+            )),
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for SortOrder {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<SortOrder, D::Error> {
+        let value = String::deserialize(deserializer)?;
+        value.parse().map_err(de::Error::custom)
+    }
+}
+
+/// Where NULLs of nullable keys sort, emitted as `NULLS FIRST`/`NULLS LAST`.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum NullsOrder {
+    First,
+    Last,
+}
+
+/// Direction to walk from the request cursor. `Backward` expects a
+/// `prev_cursor` and returns the page before it.
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq)]
+#[cfg_attr(feature = "utoipa", derive(ToSchema))]
+#[serde(rename_all = "snake_case")]
+pub enum PageDirection {
+    Forward,
+    Backward,
+}
+
+#[derive(Clone, Debug, Default, Deserialize)]
+#[cfg_attr(feature = "utoipa", derive(IntoParams), into_params(parameter_in = Query))]
 pub struct PaginationRequest {
+    #[cfg_attr(
+        feature = "utoipa",
+        param(example = "AnsiayI6WyIyMDI0LTA2LTAxVDEyOjAwOjAwWiIsIjQyIl19")
+    )]
     pub cursor: Option<String>,
+    /// Number of rows per page. `0` fetches no rows but still reports whether rows
+    /// remain after the cursor, e.g. for a count-only probe. It needs a cursor to
+    /// resume from; [`Paginator::count`] counts from the first page instead.
+    #[cfg_attr(feature = "utoipa", param(example = 20))]
     pub limit: Option<u32>,
+    #[cfg_attr(feature = "utoipa", param(example = "created_at"))]
     pub sort_by: Option<String>,
-    #[param(inline)]
+    #[cfg_attr(feature = "utoipa", param(inline))]
     pub sort_order: Option<SortOrder>,
+    #[cfg_attr(feature = "utoipa", param(inline))]
+    pub direction: Option<PageDirection>,
+    /// 1-based page number, only used by [`Paginator::offset_mode`].
+    pub page: Option<u32>,
+}
+
+impl PaginationRequest {
+    /// Requests the page of `cursor`, e.g. `Cursor::first()` rather than leaving
+    /// [`PaginationRequest::cursor`] unset.
+    pub fn with_cursor(mut self, cursor: Cursor) -> Self {
+        self.cursor = cursor.into_inner();
+        self
+    }
+
+    /// Checks the request up front, e.g. in a handler before touching the database,
+    /// failing with every problem found at once in a
+    /// [`PaginationError::InvalidRequest`]: a cursor that isn't base64, a `limit`
+    /// above `max_limit` or a `page` of 0.
+    ///
+    /// The cursor's payload is only checked when paginating, or with
+    /// [`Paginator::validate_cursor`], since that needs the paginator's keys and
+    /// signing key.
+    pub fn validate(&self, max_limit: u32) -> Result<ValidatedRequest, PaginationError> {
+        let mut errors = vec![];
+
+        if let Some(cursor) = &self.cursor {
+            if let Err(PaginationError::InvalidCursor(err)) = cursor::check_format(cursor) {
+                errors.push(err.message());
+            }
+        }
+
+        if self.limit.is_some_and(|limit| limit > max_limit) {
+            errors.push("limit is too large");
+        }
+
+        if self.page == Some(0) {
+            errors.push("page must be at least 1");
+        }
+
+        if !errors.is_empty() {
+            return Err(PaginationError::InvalidRequest(errors));
+        }
+
+        Ok(ValidatedRequest(self.clone()))
+    }
 }
 
-#[derive(Default, Serialize, ToSchema)]
+/// A [`PaginationRequest`] that passed [`PaginationRequest::validate`], which
+/// dereferences to it, e.g. to pass it on to [`Paginator::request`].
+#[derive(Clone, Debug)]
+pub struct ValidatedRequest(PaginationRequest);
+
+impl Deref for ValidatedRequest {
+    type Target = PaginationRequest;
+
+    fn deref(&self) -> &PaginationRequest {
+        &self.0
+    }
+}
+
+#[derive(Clone, Debug, Serialize)]
+#[cfg_attr(feature = "utoipa", derive(ToSchema))]
 pub struct PaginationResponse<T> {
     pub data: Vec<T>,
+    #[cfg_attr(
+        feature = "utoipa",
+        schema(example = "AnsiayI6WyIyMDI0LTA1LTMwVDA4OjE1OjAwWiIsIjE3Il19")
+    )]
     pub next_cursor: Option<String>,
+    #[cfg_attr(
+        feature = "utoipa",
+        schema(example = "AnsiayI6WyIyMDI0LTA2LTAxVDEyOjAwOjAwWiIsIjQyIl19")
+    )]
+    pub prev_cursor: Option<String>,
+    pub total: Option<u64>,
+    pub has_next_page: bool,
+    pub has_prev_page: bool,
+    /// Current page number in offset mode.
+    pub page: Option<u32>,
+    /// Number of pages in offset mode, when the total count is known.
+    pub total_pages: Option<u64>,
+    /// Page size the page was fetched with, after defaulting and clamping the
+    /// requested limit.
+    pub applied_limit: u32,
+    /// Sort order of the rows, unless the keys are sorted in different directions.
+    pub applied_sort_order: Option<SortOrder>,
+    /// Cursor of every row of `data`, resuming right after it, with
+    /// [`Paginator::with_row_cursors`].
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub cursors: Vec<String>,
+    /// How many more pages there are at least in the walked direction, up to
+    /// [`Paginator::lookahead_pages`]. 1 when there is a next page by default.
+    pub pages_remaining_at_least: usize,
+    /// The overshoot row fetched to detect the next page, i.e. the first row of
+    /// the next page in the walked direction, with [`Paginator::with_peeked_row`].
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub peeked_row: Option<T>,
+}
+
+// not derived, which would require `T: Default`
+impl<T> Default for PaginationResponse<T> {
+    fn default() -> Self {
+        PaginationResponse {
+            data: vec![],
+            next_cursor: None,
+            prev_cursor: None,
+            total: None,
+            has_next_page: false,
+            has_prev_page: false,
+            page: None,
+            total_pages: None,
+            applied_limit: 0,
+            applied_sort_order: None,
+            cursors: vec![],
+            pages_remaining_at_least: 0,
+            peeked_row: None,
+        }
+    }
+}
+
+impl<T> PaginationResponse<T> {
+    /// Maps the rows, e.g. into the DTO returned to clients, keeping the cursors
+    /// that were already retrieved from the fetched rows and the order of `data`.
+    pub fn map<U>(self, f: impl Fn(T) -> U) -> PaginationResponse<U> {
+        PaginationResponse {
+            data: self.data.into_iter().map(&f).collect(),
+            next_cursor: self.next_cursor,
+            prev_cursor: self.prev_cursor,
+            total: self.total,
+            has_next_page: self.has_next_page,
+            has_prev_page: self.has_prev_page,
+            page: self.page,
+            total_pages: self.total_pages,
+            applied_limit: self.applied_limit,
+            applied_sort_order: self.applied_sort_order,
+            cursors: self.cursors,
+            pages_remaining_at_least: self.pages_remaining_at_least,
+            peeked_row: self.peeked_row.map(f),
+        }
+    }
+
+    /// Sets the total count, and in offset mode the number of pages, e.g. from the
+    /// count of [`Paginator::paginate_and_count`].
+    pub fn with_total(mut self, total: u64) -> Self {
+        self.total = Some(total);
+        if self.page.is_some() && self.applied_limit > 0 {
+            self.total_pages = Some(total.div_ceil(self.applied_limit as u64));
+        }
+        self
+    }
 }
 
-pub struct Paginator<T> {
-    keys_: (String, String),
-    retrieve_keys_: fn(&T) -> (String, String),
+type RetrieveKeysFn<T> = Arc<dyn Fn(&T) -> Vec<Option<String>> + Send + Sync>;
+
+type ParseKeysFn = fn(Vec<Option<String>>) -> Result<(), PaginationError>;
+
+type NormalizeKeysFn = Arc<dyn Fn(Vec<Option<String>>) -> Vec<Option<String>> + Send + Sync>;
+
+type FilterFn<DB> = Arc<dyn Fn(&mut QueryBuilder<'_, DB>) + Send + Sync>;
+
+/// Builds keyset paginated queries. Paginates on Postgres unless another
+/// [`Backend`] is picked, e.g. `Paginator::<User, MySql>::new()` with the `mysql`
+/// feature.
+pub struct Paginator<T, DB: Database = Postgres> {
+    keys_: Vec<String>,
+    key_orders_: Vec<SortOrder>,
+    key_expressions_: Vec<(String, String)>,
+    key_columns_: Vec<(String, String)>,
+    key_collations_: Vec<(String, String)>,
+    seek_: Option<Vec<String>>,
+    inclusive_cursor_: bool,
+    retrieve_keys_: Option<RetrieveKeysFn<T>>,
+    normalize_keys_: Option<NormalizeKeysFn>,
     request_: PaginationRequest,
+    total_count_: bool,
+    cached_total_: Option<u64>,
+    allowed_sort_columns_: Vec<String>,
+    nulls_: Option<NullsOrder>,
+    limit_: Option<u32>,
+    default_limit_: u32,
+    max_limit_: u32,
+    cursor_: CursorConfig,
+    self_describing_: bool,
+    offset_mode_: bool,
+    window_count_: bool,
+    row_cursors_: bool,
+    display_order_: bool,
+    base_predicate_: bool,
+    wrap_subquery_: bool,
+    filters_: Vec<FilterFn<DB>>,
+    after_cursor_filters_: Vec<FilterFn<DB>>,
+    peek_row_: bool,
+    check_cursor_keys_: bool,
+    lookahead_pages_: u32,
+    _db: PhantomData<DB>,
 }
 
-impl<'a, T> Paginator<T>
-where
-    T: for<'r> FromRow<'r, PgRow> + Send + Sync + Unpin,
-{
+impl<T, DB: Database> Paginator<T, DB> {
     pub fn new() -> Self {
         Paginator {
-            keys_: (String::from(""), String::from("")),
-            retrieve_keys_: |_: &T| (String::from(""), String::from("")),
+            keys_: vec![String::from(""), String::from("")],
+            key_orders_: vec![],
+            key_expressions_: vec![],
+            key_columns_: vec![],
+            key_collations_: vec![],
+            seek_: None,
+            inclusive_cursor_: false,
+            retrieve_keys_: None,
+            normalize_keys_: None,
             request_: PaginationRequest::default(),
+            total_count_: false,
+            cached_total_: None,
+            allowed_sort_columns_: vec![],
+            nulls_: None,
+            limit_: None,
+            default_limit_: 10,
+            max_limit_: 100,
+            cursor_: CursorConfig::default(),
+            self_describing_: false,
+            offset_mode_: false,
+            window_count_: false,
+            row_cursors_: false,
+            display_order_: true,
+            base_predicate_: true,
+            wrap_subquery_: false,
+            filters_: vec![],
+            after_cursor_filters_: vec![],
+            peek_row_: false,
+            check_cursor_keys_: cfg!(debug_assertions),
+            lookahead_pages_: 1,
+            _db: PhantomData,
         }
     }
 
+    /// Paginate on two columns, e.g. `(created_at, id)`.
+    ///
+    /// The keys must be unique together, which in practice means the last key is a
+    /// unique column like the id. Otherwise rows sharing the keys of a page's last
+    /// row are skipped by the next page. See [`Paginator::check_tie_breaker`].
     pub fn keys(mut self, key1: &str, key2: &str) -> Self {
-        self.keys_ = (key1.to_string(), key2.to_string());
+        self.keys_ = vec![key1.to_string(), key2.to_string()];
+        self.key_orders_ = vec![];
         self
     }
 
-    pub fn retrieve_keys(mut self, f: fn(&T) -> (String, String)) -> Self {
-        self.retrieve_keys_ = f;
+    /// Paginate on a single column, e.g. an auto-increment id. Use together with
+    /// [`Paginator::retrieve_key`] and [`Paginator::paginate_single`].
+    pub fn single_key(mut self, key: &str) -> Self {
+        self.keys_ = vec![key.to_string()];
+        self.key_orders_ = vec![];
         self
     }
 
-    pub fn request(mut self, request: &PaginationRequest) -> Self {
-        self.request_ = request.clone();
+    /// Paginate on any number of columns, e.g. `(priority, created_at, id)`. Use
+    /// together with [`Paginator::retrieve_multi_keys`] and [`Paginator::paginate_keys`].
+    pub fn multi_keys(mut self, keys: &[&str]) -> Self {
+        self.keys_ = keys.iter().map(|key| key.to_string()).collect();
+        self.key_orders_ = vec![];
         self
     }
 
-    pub async fn paginate<K1, K2>(
-        self,
-        db: &Pool<Postgres>,
-        mut query: QueryBuilder<'a, Postgres>,
-    ) -> Result<PaginationResponse<T>, error::Error>
-    where
-        K1: 'a
-            + KeyParse
-            + std::default::Default
-            + sqlx::Encode<'a, sqlx::Postgres>
-            + sqlx::Type<sqlx::Postgres>
-            + Send,
-        K2: 'a
-            + KeyParse
-            + std::default::Default
-            + sqlx::Encode<'a, sqlx::Postgres>
-            + sqlx::Type<sqlx::Postgres>
-            + Send,
-    {
-        let mut cursor_values: Vec<String> = vec![];
-        if let Some(cursor) = self.request_.cursor {
-            cursor_values = parse_cursor(cursor)?;
-        }
-
-        let mut smaller = true;
-        if let Some(o) = self.request_.sort_order.clone() {
-            smaller = o == SortOrder::Desc;
+    /// Paginate on columns with a direction each, e.g.
+    /// `[("priority", SortOrder::Asc), ("created_at", SortOrder::Desc)]`. The
+    /// directions are fixed, so the request's `sort_order` is ignored.
+    pub fn keys_with_order(mut self, keys: &[(&str, SortOrder)]) -> Self {
+        self.keys_ = keys.iter().map(|(key, _)| key.to_string()).collect();
+        self.key_orders_ = keys.iter().map(|(_, order)| order.clone()).collect();
+        self
+    }
+
+    /// Compare and order the key `alias` by the SQL `expression` instead, e.g.
+    /// `key_expression("sort_name", "lower(name)")` for a base query selecting
+    /// `lower(name) AS sort_name`. The cursor predicate can't reference the alias,
+    /// so it repeats the expression, which should match an index to be fast.
+    ///
+    /// The expression is pushed into the query as is, so it must never come from a
+    /// client. It also applies when the alias is picked through
+    /// [`PaginationRequest::sort_by`].
+    pub fn key_expression(mut self, alias: &str, expression: &str) -> Self {
+        self.key_expressions_
+            .push((alias.to_string(), expression.to_string()));
+        self
+    }
+
+    /// Compare and order the key `alias` by the qualified `column` of one of the
+    /// joined tables, e.g. `key_column("post_id", "posts.id")` for a base query
+    /// selecting `posts.id AS post_id` from `posts JOIN authors`, where a plain `id`
+    /// would be ambiguous. Unlike [`Paginator::key_expression`], the column is
+    /// validated like the keys.
+    ///
+    /// Keys selected under their own name can be qualified directly instead, e.g.
+    /// `keys("posts.created_at", "posts.id")`.
+    pub fn key_column(mut self, alias: &str, column: &str) -> Self {
+        self.key_columns_
+            .push((alias.to_string(), column.to_string()));
+        self
+    }
+
+    /// Compares and orders the text key `key` under `collation`, e.g.
+    /// `collate("name", "und-x-icu")` on Postgres or `collate("name", "NOCASE")` on
+    /// SQLite, so that pages follow the same order as an index built with it.
+    pub fn collate(mut self, key: &str, collation: &str) -> Self {
+        self.key_collations_
+            .push((key.to_string(), collation.to_string()));
+        self
+    }
+
+    /// Checks that the last key is one of `unique_columns`, so that the keys are
+    /// unique together and no row is skipped or repeated across pages. Meant to be
+    /// called once, e.g. in a test of the endpoint's paginator.
+    ///
+    /// In debug builds, a page whose last row has the same keys as the row after it
+    /// is also logged as a warning.
+    pub fn check_tie_breaker(&self, unique_columns: &[&str]) -> Result<(), PaginationError> {
+        match self.keys_.last() {
+            Some(key) if unique_columns.contains(&key.as_str()) => Ok(()),
+            _ => Err(PaginationError::Configuration(format!(
+                "last key of {:?} is not a unique column",
+                self.keys_
+            ))),
         }
+    }
+
+    pub fn retrieve_keys(
+        mut self,
+        f: impl Fn(&T) -> (String, String) + Send + Sync + 'static,
+    ) -> Self {
+        self.retrieve_keys_ = Some(Arc::new(move |row: &T| {
+            let (key1, key2) = f(row);
+            vec![Some(key1), Some(key2)]
+        }));
+        self
+    }
+
+    pub fn retrieve_key(mut self, f: impl Fn(&T) -> String + Send + Sync + 'static) -> Self {
+        self.retrieve_keys_ = Some(Arc::new(move |row: &T| vec![Some(f(row))]));
+        self
+    }
+
+    pub fn retrieve_multi_keys(
+        mut self,
+        f: impl Fn(&T) -> Vec<String> + Send + Sync + 'static,
+    ) -> Self {
+        self.retrieve_keys_ = Some(Arc::new(move |row: &T| {
+            f(row).into_iter().map(Some).collect()
+        }));
+        self
+    }
+
+    /// Retrieve the keys as typed values, e.g. `|post: &Post| (post.created_at, post.id)`,
+    /// which are written into the cursor with [`KeyParse::format_key`], the format
+    /// [`KeyParse::parse_key`] reads back. Unlike formatting the keys by hand, this
+    /// can't produce a cursor the next request fails to parse, or parses into
+    /// another value (e.g. a timestamp losing its fraction).
+    ///
+    /// NULL keys (`Option<K>`) stay NULL in the cursor, like with
+    /// [`Paginator::retrieve_nullable_keys`].
+    pub fn retrieve_typed_keys<K: KeyTuple>(
+        mut self,
+        f: impl Fn(&T) -> K + Send + Sync + 'static,
+    ) -> Self {
+        self.retrieve_keys_ = Some(Arc::new(move |row: &T| f(row).format_keys()));
+        self
+    }
+
+    /// Like [`Paginator::retrieve_multi_keys`], for keys that may be NULL. Such keys
+    /// are parsed as `Option<K>` and should be paired with [`Paginator::nulls`].
+    pub fn retrieve_nullable_keys(
+        mut self,
+        f: impl Fn(&T) -> Vec<Option<String>> + Send + Sync + 'static,
+    ) -> Self {
+        self.retrieve_keys_ = Some(Arc::new(f));
+        self
+    }
 
-        let mut limit = 10;
-        if let Some(l) = self.request_.limit {
-            if l > 0 && l <= 100 {
-                limit = l;
+    /// Rewrites the keys retrieved from a row before they go in a cursor, e.g. to
+    /// truncate a timestamp to the precision of the functional index the keys are
+    /// compared against. Applies to both keys when neither is NULL, the keys are
+    /// left as is otherwise.
+    pub fn normalize_keys(
+        mut self,
+        f: impl Fn((String, String)) -> (String, String) + Send + Sync + 'static,
+    ) -> Self {
+        self.normalize_keys_ = Some(Arc::new(move |keys: Vec<Option<String>>| {
+            match <[Option<String>; 2]>::try_from(keys) {
+                Ok([Some(key1), Some(key2)]) => {
+                    let (key1, key2) = f((key1, key2));
+                    vec![Some(key1), Some(key2)]
+                }
+                Ok(keys) => keys.into(),
+                Err(keys) => keys,
             }
-        }
+        }));
+        self
+    }
 
-        let (key1, key2) = self.keys_.clone();
+    /// Like [`Paginator::normalize_keys`], for any number of keys, `None` for NULL.
+    pub fn normalize_nullable_keys(
+        mut self,
+        f: impl Fn(Vec<Option<String>>) -> Vec<Option<String>> + Send + Sync + 'static,
+    ) -> Self {
+        self.normalize_keys_ = Some(Arc::new(f));
+        self
+    }
 
-        // we add 1 to limit to ensure there's a next page (the extra record will be discarded)
-        let pagination = sqlx_page::Pagination::new(smaller, limit + 1, vec![key1, key2]);
+    /// Start the first page at the row with the key values `key1` and `key2`,
+    /// including it, e.g. to deep-link to a bookmarked row without building a
+    /// cursor. The values are parsed like cursor values when paginating, and a
+    /// request's cursor takes precedence, so following pages go on from there.
+    pub fn seek_to(self, key1: String, key2: String) -> Self {
+        self.seek_to_keys(vec![key1, key2])
+    }
 
-        if cursor_values.len() == 2 {
-            query.push(" AND");
+    /// Like [`Paginator::seek_to`], for any number of keys.
+    pub fn seek_to_keys(mut self, keys: Vec<String>) -> Self {
+        self.seek_ = Some(keys);
+        self
+    }
 
-            let key1: K1 = parse_key(cursor_values[0].clone())?;
-            let key2: K2 = parse_key(cursor_values[1].clone())?;
+    /// Include the row the request's cursor points at as the first row of the page,
+    /// e.g. to refresh a list from the row at its top, by comparing the keys with
+    /// `>=` instead of `>`.
+    ///
+    /// Cursors still point at the first and last rows of a page, so walking pages
+    /// this way returns the last row of every page again as the first of the next
+    /// one. Use it for a single request rather than a paginator walking every page.
+    pub fn inclusive_cursor(mut self, inclusive: bool) -> Self {
+        self.inclusive_cursor_ = inclusive;
+        self
+    }
 
-            pagination.push_where2(&mut query, Some((key1, key2)));
-        }
+    pub fn request(mut self, request: &PaginationRequest) -> Self {
+        self.request_ = request.clone();
+        self
+    }
 
-        pagination.push_order_by(&mut query);
-        pagination.push_limit(&mut query);
+    /// Columns a client may pick through [`PaginationRequest::sort_by`]. The chosen
+    /// column replaces the first key, the remaining keys stay as tie-breakers, so the
+    /// key retrieval must return the value of whichever column was picked. Unknown
+    /// columns are rejected; `sort_by` is ignored when no columns are allowed.
+    pub fn allowed_sort_columns(mut self, columns: &[&str]) -> Self {
+        self.allowed_sort_columns_ = columns.iter().map(|column| column.to_string()).collect();
+        self
+    }
 
-        let data = match query.build_query_as::<T>().fetch_all(db).await {
-            Ok(data) => data,
-            Err(err) => {
-                log::error!("failed to run pagination query: {}", err);
-                return Err(error::internal());
-            }
-        };
+    /// Fetch the first `n` rows only, e.g. the newest 10 of a feed, without a
+    /// request: the request's cursor, direction and page are dropped, as is
+    /// [`Paginator::seek_to`]. The response still has a `next_cursor` to go on
+    /// paging with a request of the same sort.
+    pub fn first(mut self, n: u32) -> Self {
+        self.request_.cursor = None;
+        self.request_.direction = None;
+        self.request_.page = None;
+        self.seek_ = None;
+        self.limit_ = Some(n);
+        self
+    }
 
-        let mut res: PaginationResponse<T> = PaginationResponse {
-            data,
-            next_cursor: None,
-        };
+    /// Page size fixed in code, e.g. for internal jobs. It wins over the request's
+    /// limit, and is clamped to [`Paginator::max_limit`] just the same.
+    pub fn limit(mut self, limit: u32) -> Self {
+        self.limit_ = Some(limit);
+        self
+    }
 
-        // if we got limit+1 records, we have a next page
-        if res.data.len() == (limit as usize) + 1 {
-            res.data.remove(res.data.len() - 1);
+    /// Page size used when neither [`Paginator::limit`] nor the request set one.
+    /// Defaults to 10.
+    pub fn default_limit(mut self, limit: u32) -> Self {
+        self.default_limit_ = limit;
+        self
+    }
 
-            if let Some(last) = res.data.last() {
-                let (key1, key2) = (self.retrieve_keys_)(last);
-                let keys = vec![key1, key2];
+    /// Largest page size a request may ask for; larger limits are clamped to it.
+    /// Defaults to 100, and can't be raised above 100,000 rows per page.
+    pub fn max_limit(mut self, limit: u32) -> Self {
+        self.max_limit_ = limit;
+        self
+    }
 
-                let cursor_json = match serde_json::to_vec(&keys) {
-                    Ok(cursor_json) => cursor_json,
-                    Err(err) => {
-                        log::error!("failed to serialize next cursor: {}", err);
-                        return Err(error::internal());
-                    }
-                };
+    /// Sort NULLs of every key first or last, and account for them in the cursor
+    /// comparison so that paging across the NULL boundary neither skips nor
+    /// repeats rows.
+    ///
+    /// Without it NULLs sort where the database puts them, last ascending on
+    /// Postgres and first on MySQL and SQLite, and a cursor on a NULL key still
+    /// goes on past the NULLs. Walking from the values into the NULLs needs it.
+    /// MySQL has no `NULLS FIRST`/`NULLS LAST`, paginating with it fails there.
+    pub fn nulls(mut self, nulls: NullsOrder) -> Self {
+        self.nulls_ = Some(nulls);
+        self
+    }
 
-                let cursor = BASE64_URL_SAFE.encode(&cursor_json).to_string();
-                res.next_cursor = Some(cursor);
-            }
-        }
+    /// Sign cursors with HMAC-SHA256 under `key`, rejecting cursors that were
+    /// tampered with or not signed with the same key.
+    pub fn signing_key(mut self, key: &[u8]) -> Self {
+        self.cursor_.signing_key = Some(key.to_vec());
+        self
+    }
 
-        Ok(res)
+    /// Base64 alphabet and padding of the cursors, [`CursorEncoding::UrlSafe`] by
+    /// default. Cursors only decode with the encoding they were issued with.
+    pub fn cursor_encoding(mut self, encoding: CursorEncoding) -> Self {
+        self.cursor_.encoding = encoding;
+        self
     }
-}
 
-fn parse_cursor(cursor: String) -> Result<Vec<String>, error::Error> {
-    let bytes = match BASE64_URL_SAFE.decode(&cursor) {
-        Ok(bytes) => bytes,
-        Err(_) => {
-            return Err(error::invalid_argument_with_message("invalid cursor"));
-        }
-    };
+    /// Accept cursors carrying no sort, from before cursors had one or built with
+    /// [`encode_cursor`], under whatever sort the paginator has. They're rejected
+    /// with [`CursorError::SortMismatch`] by default, since nothing tells which keys
+    /// and directions they were issued for.
+    pub fn accept_legacy_cursors(mut self, accept: bool) -> Self {
+        self.cursor_.accept_unsorted = accept;
+        self
+    }
 
-    let values: Vec<String> = match serde_json::from_slice(&bytes) {
-        Ok(values) => values,
-        Err(_) => {
-            return Err(error::invalid_argument_with_message("invalid cursor"));
-        }
-    };
+    /// Encode the limit into the cursors too, and continue with the limit, sort and
+    /// `sort_by` of a request's cursor unless the request sets them, so that clients
+    /// only pass the cursor on to get the next page. The sort is in every cursor
+    /// already, to reject cursors issued under another one.
+    pub fn self_describing_cursors(mut self, self_describing: bool) -> Self {
+        self.self_describing_ = self_describing;
+        self
+    }
+
+    /// Serialize the cursors with `codec`, e.g. `MessagePackCodec` (`msgpack`
+    /// feature) for shorter cursors. Cursors issued with [`JsonCodec`], the
+    /// default, keep decoding after switching.
+    pub fn cursor_codec(mut self, codec: impl CursorCodec + 'static) -> Self {
+        self.cursor_.codec = Some(Arc::new(codec));
+        self
+    }
+
+    /// Encrypt cursors with ChaCha20-Poly1305 under `key`, so that they don't expose
+    /// the key values to clients. This also authenticates cursors, making
+    /// [`Paginator::signing_key`] unnecessary.
+    #[cfg(feature = "crypto")]
+    pub fn encryption_key(mut self, key: &[u8; 32]) -> Self {
+        self.cursor_.encryption_key = Some(*key);
+        self
+    }
+
+    /// Also count every row matching the base query into
+    /// [`PaginationResponse::total`]. The count needs to rebuild the base query, so
+    /// this only takes effect with [`Paginator::paginate_keys_with`].
+    pub fn with_total_count(mut self, total_count: bool) -> Self {
+        self.total_count_ = total_count;
+        self
+    }
+
+    /// Report `total` as [`PaginationResponse::total`] instead of counting the rows,
+    /// e.g. a count the caller got from [`Paginator::count`] and caches across the
+    /// requests paging through the same filters. This skips the count query of
+    /// [`Paginator::with_total_count`], at the cost of a total (and number of pages)
+    /// that's stale once rows are added or removed.
+    pub fn cached_total(mut self, total: u64) -> Self {
+        self.cached_total_ = Some(total);
+        self
+    }
+
+    /// Paginate with `OFFSET`/`LIMIT` on [`PaginationRequest::page`] instead of a
+    /// cursor, for screens that need to jump to an arbitrary page. The keys still
+    /// define the order, no cursors are returned, and combined with
+    /// [`Paginator::with_total_count`] the response reports the number of pages.
+    ///
+    /// Deep offsets get slow on large tables, prefer cursors where possible.
+    pub fn offset_mode(mut self) -> Self {
+        self.offset_mode_ = true;
+        self
+    }
+
+    /// Detect the next page from a `COUNT(*) OVER ()` of the rows after the cursor
+    /// instead of fetching one row more than the limit. The base query is wrapped in
+    /// a subquery for this, so this only takes effect with
+    /// [`Paginator::paginate_keys_with`], and the base query must select the key
+    /// columns.
+    ///
+    /// Counting the window visits every row after the cursor, while the overshoot
+    /// row stops after `limit + 1` rows of the index. This only pays off when rows
+    /// are wide and few remain after the cursor; the default is cheaper otherwise.
+    pub fn detect_next_via_window(mut self) -> Self {
+        self.window_count_ = true;
+        self
+    }
+
+    /// Whether the base query already ends in a `WHERE` clause, which the cursor
+    /// predicate is then joined to with `AND`. Defaults to `true`; set it to `false`
+    /// for a base query like `SELECT * FROM users` to have `WHERE` emitted instead.
+    pub fn has_base_predicate(mut self, has_base_predicate: bool) -> Self {
+        self.base_predicate_ = has_base_predicate;
+        self
+    }
+
+    /// Selects from the base query as a subquery, `SELECT * FROM (...) AS sub`, and
+    /// filters and orders that instead, e.g. for a CTE base query like
+    /// `WITH ranked AS (...) SELECT * FROM ranked` whose keys are the columns it
+    /// outputs. The base query then needs no `WHERE` clause of its own, and the
+    /// keys can't be qualified with its tables. Only paginating with
+    /// [`Paginator::paginate_keys_with`] or the like can wrap the base query.
+    pub fn wrap_subquery(mut self, wrap_subquery: bool) -> Self {
+        self.wrap_subquery_ = wrap_subquery;
+        self
+    }
+
+    /// Always filter on the SQL predicate `fragment`, e.g. `deleted_at IS NULL` to
+    /// leave out soft-deleted rows. Filters are joined to the base query ahead of
+    /// the cursor predicate, honoring [`Paginator::has_base_predicate`], and are
+    /// also counted by [`Paginator::with_total_count`] and [`Paginator::count`].
+    ///
+    /// The fragment is pushed into the query as is, so it must never come from a
+    /// client; use [`Paginator::filter_with`] to bind values.
+    pub fn filter(self, fragment: &str) -> Self {
+        let fragment = fragment.to_string();
+        self.filter_with(move |query| {
+            query.push(&fragment);
+        })
+    }
+
+    /// Like [`Paginator::filter`], with the predicate pushed by `f`, e.g.
+    /// `|query| { query.push("tenant_id = ").push_bind(tenant_id); }`.
+    pub fn filter_with(
+        mut self,
+        f: impl Fn(&mut QueryBuilder<'_, DB>) + Send + Sync + 'static,
+    ) -> Self {
+        self.filters_.push(Arc::new(f));
+        self
+    }
+
+    /// Like [`Paginator::filter`], but joined after the cursor predicate, e.g.
+    /// `author_id NOT IN (...)` to hide the authors a user blocked from a feed.
+    ///
+    /// The cursor predicate is parenthesized and cursors only carry the keys of a
+    /// returned row, so cursors stay valid when the excluded rows change between
+    /// pages, even if the row a cursor points at is excluded by then: the next page
+    /// starts right after its keys all the same.
+    pub fn filter_after_cursor(self, fragment: &str) -> Self {
+        let fragment = fragment.to_string();
+        self.filter_after_cursor_with(move |query| {
+            query.push(&fragment);
+        })
+    }
+
+    /// Like [`Paginator::filter_after_cursor`], with the predicate pushed by `f` as
+    /// with [`Paginator::filter_with`].
+    pub fn filter_after_cursor_with(
+        mut self,
+        f: impl Fn(&mut QueryBuilder<'_, DB>) + Send + Sync + 'static,
+    ) -> Self {
+        self.after_cursor_filters_.push(Arc::new(f));
+        self
+    }
+
+    /// Keep the overshoot row in [`PaginationResponse::peeked_row`] instead of
+    /// discarding it, e.g. to prefetch it. It's not fetched with
+    /// [`Paginator::detect_next_via_window`].
+    pub fn with_peeked_row(mut self, peek_row: bool) -> Self {
+        self.peek_row_ = peek_row;
+        self
+    }
+
+    /// Parse the keys retrieved for every issued cursor back as the key types, and
+    /// fail the page with a [`PaginationError::Configuration`] when they don't, e.g.
+    /// a `DateTime<Utc>` key retrieved as `2024-01-01`. Otherwise the cursor is only
+    /// rejected by the next request. On by default in debug builds.
+    pub fn validate_cursor_roundtrip(mut self, check: bool) -> Self {
+        self.check_cursor_keys_ = check;
+        self
+    }
+
+    /// Look up to `pages` pages ahead for [`PaginationResponse::pages_remaining_at_least`],
+    /// e.g. for clients prefetching a few pages, by fetching `limit * pages + 1`
+    /// rows instead of `limit + 1`. The rows beyond the limit are discarded all the
+    /// same, so keep it small. 1 by default, 0 is treated as 1.
+    pub fn lookahead_pages(mut self, pages: u32) -> Self {
+        self.lookahead_pages_ = pages.max(1);
+        self
+    }
 
-    if values.len() != 2 {
-        return Err(error::invalid_argument_with_message("invalid cursor"));
+    /// Also return a cursor for every row in [`PaginationResponse::cursors`], to
+    /// resume from any of them. This runs [`Paginator::retrieve_keys`] once per row.
+    pub fn with_row_cursors(mut self, row_cursors: bool) -> Self {
+        self.row_cursors_ = row_cursors;
+        self
     }
 
-    Ok(values)
+    /// Return the rows of a page walked backward in the sort order, the same as
+    /// paging forward over them (the default), or with `false` in the order they
+    /// were walked, i.e. starting with the row right before the cursor, e.g. for a
+    /// chat prepending older messages one by one. `next_cursor` and `prev_cursor`
+    /// are the same either way.
+    pub fn preserve_display_order(mut self, display_order: bool) -> Self {
+        self.display_order_ = display_order;
+        self
+    }
 }
 
-pub trait KeyParse: Sized {
-    fn parse_key(key: String) -> Result<Self, error::Error>;
+impl<T, DB: Database> Clone for Paginator<T, DB> {
+    fn clone(&self) -> Self {
+        Paginator {
+            keys_: self.keys_.clone(),
+            key_orders_: self.key_orders_.clone(),
+            key_expressions_: self.key_expressions_.clone(),
+            key_columns_: self.key_columns_.clone(),
+            key_collations_: self.key_collations_.clone(),
+            seek_: self.seek_.clone(),
+            inclusive_cursor_: self.inclusive_cursor_,
+            retrieve_keys_: self.retrieve_keys_.clone(),
+            normalize_keys_: self.normalize_keys_.clone(),
+            request_: self.request_.clone(),
+            total_count_: self.total_count_,
+            cached_total_: self.cached_total_,
+            allowed_sort_columns_: self.allowed_sort_columns_.clone(),
+            nulls_: self.nulls_.clone(),
+            limit_: self.limit_,
+            default_limit_: self.default_limit_,
+            max_limit_: self.max_limit_,
+            cursor_: self.cursor_.clone(),
+            self_describing_: self.self_describing_,
+            offset_mode_: self.offset_mode_,
+            window_count_: self.window_count_,
+            row_cursors_: self.row_cursors_,
+            display_order_: self.display_order_,
+            base_predicate_: self.base_predicate_,
+            wrap_subquery_: self.wrap_subquery_,
+            filters_: self.filters_.clone(),
+            after_cursor_filters_: self.after_cursor_filters_.clone(),
+            peek_row_: self.peek_row_,
+            check_cursor_keys_: self.check_cursor_keys_,
+            lookahead_pages_: self.lookahead_pages_,
+            _db: PhantomData,
+        }
+    }
 }
 
-impl KeyParse for String {
-    fn parse_key(key: String) -> Result<String, error::Error> {
-        Ok(key)
+/// Leaves out the signing and encryption keys, and shows `retrieve_keys` only as
+/// being set or not.
+impl<T, DB: Database> fmt::Debug for Paginator<T, DB> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Paginator")
+            .field("keys", &self.keys_)
+            .field("key_orders", &self.key_orders_)
+            .field("key_expressions", &self.key_expressions_)
+            .field("key_columns", &self.key_columns_)
+            .field("key_collations", &self.key_collations_)
+            .field("seek", &self.seek_)
+            .field("inclusive_cursor", &self.inclusive_cursor_)
+            .field(
+                "retrieve_keys",
+                &self.retrieve_keys_.as_ref().map(|_| "<fn>"),
+            )
+            .field(
+                "normalize_keys",
+                &self.normalize_keys_.as_ref().map(|_| "<fn>"),
+            )
+            .field("request", &self.request_)
+            .field("total_count", &self.total_count_)
+            .field("cached_total", &self.cached_total_)
+            .field("allowed_sort_columns", &self.allowed_sort_columns_)
+            .field("nulls", &self.nulls_)
+            .field("limit", &self.limit_)
+            .field("default_limit", &self.default_limit_)
+            .field("max_limit", &self.max_limit_)
+            .field("cursor_encoding", &self.cursor_.encoding)
+            .field("accept_legacy_cursors", &self.cursor_.accept_unsorted)
+            .field("self_describing_cursors", &self.self_describing_)
+            .field("offset_mode", &self.offset_mode_)
+            .field("window_count", &self.window_count_)
+            .field("row_cursors", &self.row_cursors_)
+            .field("display_order", &self.display_order_)
+            .field("base_predicate", &self.base_predicate_)
+            .field("wrap_subquery", &self.wrap_subquery_)
+            .field("filters", &self.filters_.len())
+            .field("after_cursor_filters", &self.after_cursor_filters_.len())
+            .field("peek_row", &self.peek_row_)
+            .field("check_cursor_keys", &self.check_cursor_keys_)
+            .field("lookahead_pages", &self.lookahead_pages_)
+            .finish_non_exhaustive()
     }
 }
 
-impl KeyParse for DateTime<Utc> {
-    fn parse_key(key: String) -> Result<DateTime<Utc>, error::Error> {
-        let res: DateTime<Utc> = match key.parse::<DateTime<Utc>>() {
-            Ok(res) => res,
-            Err(_) => {
-                return Err(error::invalid_argument_with_message(
-                    "failed to parse date time from string",
-                ));
+impl<T, DB: Database> Default for Paginator<T, DB> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<'a, T, DB> Paginator<T, DB>
+where
+    DB: Backend,
+    T: for<'r> FromRow<'r, DB::Row> + Send + Sync + Unpin + 'static,
+{
+    /// Paginates `query`, a base query ending in its `WHERE` clause (see
+    /// [`Paginator::has_base_predicate`]). It must select every key column, which
+    /// the cursors are retrieved from, or paginating fails with a
+    /// [`PaginationError::Configuration`] naming the missing column.
+    ///
+    /// It runs on `db`, a pool (`&pool`) or a connection, e.g. `&mut *transaction`
+    /// to see the same snapshot as the transaction's other queries. Every method
+    /// running queries takes one the same way, but for
+    /// [`Paginator::paginate_stream`] and [`Paginator::paginate_all`], which read
+    /// every page from a pool.
+    ///
+    /// The base query may already have values bound with
+    /// [`QueryBuilder::push_bind`], e.g. `WHERE tenant_id = $1`. The cursor values
+    /// are bound after them, and [`QueryBuilder`] numbers every placeholder in the
+    /// order it's pushed, so they never collide.
+    pub async fn paginate<K1, K2>(
+        self,
+        db: impl Acquire<'_, Database = DB>,
+        query: QueryBuilder<'a, DB>,
+    ) -> Result<PaginationResponse<T>, PaginationError>
+    where
+        (K1, K2): CursorKeys<'a, DB>,
+    {
+        self.paginate_keys::<(K1, K2)>(db, query).await
+    }
+
+    pub async fn paginate_single<K1>(
+        self,
+        db: impl Acquire<'_, Database = DB>,
+        query: QueryBuilder<'a, DB>,
+    ) -> Result<PaginationResponse<T>, PaginationError>
+    where
+        (K1,): CursorKeys<'a, DB>,
+    {
+        self.paginate_keys::<(K1,)>(db, query).await
+    }
+
+    pub async fn paginate_keys<K: CursorKeys<'a, DB>>(
+        mut self,
+        db: impl Acquire<'_, Database = DB>,
+        query: QueryBuilder<'a, DB>,
+    ) -> Result<PaginationResponse<T>, PaginationError> {
+        self.check_query_builder()?;
+        let mut conn = acquire(db).await?;
+        self.fetch_page::<K>(&mut conn, query).await
+    }
+
+    /// Like [`Paginator::paginate_keys`], but returns a Relay [`Connection`] with a
+    /// cursor for every row, so [`Paginator::retrieve_keys`] runs once per row.
+    #[cfg(feature = "relay")]
+    pub async fn paginate_connection<K: CursorKeys<'a, DB>>(
+        mut self,
+        db: impl Acquire<'_, Database = DB>,
+        query: QueryBuilder<'a, DB>,
+    ) -> Result<Connection<T>, PaginationError> {
+        self.check_query_builder()?;
+        let mut conn = acquire(db).await?;
+        let (query, plan) = self.build_page::<K>(query)?;
+        let parse_keys = plan.parse_keys;
+        let res = self
+            .fetch_planned(&mut conn, query, plan, |row| row)
+            .await?;
+        self.to_connection(res, parse_keys)
+    }
+
+    /// Like [`Paginator::paginate_keys`], but returns the rows keyed by the value
+    /// of their first key as retrieved with [`Paginator::retrieve_keys`], e.g. for
+    /// clients looking rows up by id. The first key must be unique, e.g. the id
+    /// with [`Paginator::single_key`], or paginating fails with a
+    /// [`PaginationError::Configuration`] once a page repeats a value.
+    #[cfg(feature = "indexed")]
+    pub async fn paginate_indexed<K: CursorKeys<'a, DB>>(
+        mut self,
+        db: impl Acquire<'_, Database = DB>,
+        query: QueryBuilder<'a, DB>,
+    ) -> Result<IndexedPage<T>, PaginationError> {
+        self.check_query_builder()?;
+        let mut conn = acquire(db).await?;
+        let res = self.fetch_page::<K>(&mut conn, query).await?;
+        self.to_indexed(res)
+    }
+
+    /// Like [`Paginator::paginate`], with the base query pushed by `base_query` as
+    /// with [`Paginator::paginate_keys_with`].
+    pub async fn paginate_with<K1, K2, F>(
+        self,
+        db: impl Acquire<'_, Database = DB>,
+        base_query: F,
+    ) -> Result<PaginationResponse<T>, PaginationError>
+    where
+        (K1, K2): CursorKeys<'a, DB>,
+        F: Fn(&mut QueryBuilder<'a, DB>),
+    {
+        self.paginate_keys_with::<(K1, K2), F>(db, base_query).await
+    }
+
+    /// Like [`Paginator::paginate_single`], with the base query pushed by
+    /// `base_query` as with [`Paginator::paginate_keys_with`].
+    pub async fn paginate_single_with<K1, F>(
+        self,
+        db: impl Acquire<'_, Database = DB>,
+        base_query: F,
+    ) -> Result<PaginationResponse<T>, PaginationError>
+    where
+        (K1,): CursorKeys<'a, DB>,
+        F: Fn(&mut QueryBuilder<'a, DB>),
+    {
+        self.paginate_keys_with::<(K1,), F>(db, base_query).await
+    }
+
+    /// Like [`Paginator::paginate_keys`], but takes a function pushing the base
+    /// query (e.g. `SELECT ... FROM users WHERE tenant_id = $1`) instead of a built
+    /// [`QueryBuilder`], so the paginator owns the query's construction.
+    ///
+    /// The same function can be kept and reused for every request, and the
+    /// paginator can build the query more than once, which
+    /// [`Paginator::with_total_count`] and [`Paginator::paginate_stream`] need. It
+    /// pushes into a builder rather than returning one so that it can also be
+    /// wrapped, e.g. in `SELECT COUNT(*) FROM (...)`:
+    ///
+    /// ```ignore
+    /// let base_query = |query: &mut QueryBuilder<Postgres>| {
+    ///     query.push("SELECT id, name FROM users WHERE tenant_id = ");
+    ///     query.push_bind(tenant_id);
+    /// };
+    ///
+    /// let res = Paginator::<User>::new()
+    ///     .single_key("id")
+    ///     .retrieve_key(|user: &User| user.id.to_string())
+    ///     .request(&request)
+    ///     .paginate_single_with::<i64, _>(&pool, &base_query)
+    ///     .await?;
+    /// ```
+    pub async fn paginate_keys_with<K, F>(
+        mut self,
+        db: impl Acquire<'_, Database = DB>,
+        base_query: F,
+    ) -> Result<PaginationResponse<T>, PaginationError>
+    where
+        K: CursorKeys<'a, DB>,
+        F: Fn(&mut QueryBuilder<'a, DB>),
+    {
+        // the count and the page run on the same connection, so that within a
+        // transaction they see the same rows
+        let mut conn = acquire(db).await?;
+
+        let mut total = self.cached_total_;
+        if total.is_none() && self.total_count_ {
+            let mut count = self.count_query(&base_query);
+            total = Some(fetch_total(&mut count, &mut conn).await?);
+        }
+
+        let query = self.base_page_query(&base_query);
+        let res = self.fetch_page::<K>(&mut conn, query).await?;
+
+        match total {
+            Some(total) => Ok(res.with_total(total)),
+            None => Ok(res),
+        }
+    }
+
+    /// Splits [`Paginator::paginate_keys_with`] with [`Paginator::with_total_count`]
+    /// into the page and the count, to run them concurrently instead of one after
+    /// the other:
+    ///
+    /// ```ignore
+    /// let (page, total) = paginator.paginate_and_count::<(i64,), _>(&pool, &base_query);
+    /// let (page, total) = tokio::join!(page, total);
+    /// let res = page?.with_total(total?);
+    /// ```
+    ///
+    /// Each future acquires its own connection from `pool`, so they take two
+    /// connections at once, and don't see the same snapshot as a transaction's
+    /// count would. The count is the cached total without running a query when one
+    /// is set with [`Paginator::cached_total`].
+    pub fn paginate_and_count<K, F>(
+        mut self,
+        pool: &'a Pool<DB>,
+        base_query: F,
+    ) -> (
+        impl Future<Output = Result<PaginationResponse<T>, PaginationError>> + 'a,
+        impl Future<Output = Result<u64, PaginationError>> + 'a,
+    )
+    where
+        K: CursorKeys<'a, DB> + 'a,
+        F: Fn(&mut QueryBuilder<'a, DB>),
+    {
+        let cached_total = self.cached_total_;
+        let mut count = self.count_query(&base_query);
+
+        let query = self.base_page_query(&base_query);
+
+        let page = async move {
+            let mut conn = acquire(pool).await?;
+            self.fetch_page::<K>(&mut conn, query).await
+        };
+
+        let count = async move {
+            if let Some(total) = cached_total {
+                return Ok(total);
             }
+
+            let mut conn = acquire(pool).await?;
+            fetch_total(&mut count, &mut conn).await
         };
-        Ok(res)
+
+        (page, count)
     }
-}
 
-fn parse_key<K: KeyParse>(key: String) -> Result<K, error::Error> {
-    K::parse_key(key)
+    /// Walks every page of the base query forward, following `next_cursor` until the
+    /// last page, e.g. for background jobs scanning a whole table. The stream ends
+    /// after the first error. Each page is the same as with
+    /// [`Paginator::paginate_keys_with`], so in offset mode only one page is read.
+    pub fn paginate_stream<K, F>(
+        self,
+        db: &'a Pool<DB>,
+        base_query: F,
+    ) -> impl Stream<Item = Result<Vec<T>, PaginationError>> + 'a
+    where
+        K: CursorKeys<'a, DB> + 'a,
+        F: Fn(&mut QueryBuilder<'a, DB>) + 'a,
+    {
+        stream::unfold(Some((self, base_query)), move |state| async move {
+            let (paginator, base_query) = state?;
+
+            let res = paginator
+                .clone()
+                .paginate_keys_with::<K, _>(db, &base_query)
+                .await;
+
+            match res {
+                Ok(res) => {
+                    let next = res.next_cursor.map(|cursor| {
+                        let mut paginator = paginator;
+                        paginator.request_.cursor = Some(cursor);
+                        (paginator, base_query)
+                    });
+                    Some((Ok(res.data), next))
+                }
+                Err(err) => Some((Err(err), None)),
+            }
+        })
+    }
+
+    /// Collects every row of the base query by walking its pages like
+    /// [`Paginator::paginate_stream`], e.g. for exports. Fails once more than
+    /// `max_rows` rows were read, so a mistaken call can't run out of memory.
+    pub async fn paginate_all<K, F>(
+        self,
+        db: &'a Pool<DB>,
+        base_query: F,
+        max_rows: usize,
+    ) -> Result<Vec<T>, PaginationError>
+    where
+        K: CursorKeys<'a, DB> + 'a,
+        F: Fn(&mut QueryBuilder<'a, DB>) + 'a,
+    {
+        let mut rows = vec![];
+
+        let mut pages = std::pin::pin!(self.paginate_stream::<K, F>(db, base_query));
+        while let Some(page) = pages.next().await {
+            rows.extend(page?);
+            if rows.len() > max_rows {
+                return Err(PaginationError::TooManyRows(max_rows));
+            }
+        }
+
+        Ok(rows)
+    }
+
+    /// Builds the page query [`Paginator::paginate_keys`] would run, with the cursor,
+    /// ORDER BY and LIMIT clauses appended to `query`, to run it later with
+    /// [`PaginatedQuery::execute`], e.g. on a transaction. The cursors are built from
+    /// the rows when it runs.
+    pub fn build<K: CursorKeys<'a, DB>>(
+        mut self,
+        query: QueryBuilder<'a, DB>,
+    ) -> Result<PaginatedQuery<'a, T, DB>, PaginationError> {
+        self.check_query_builder()?;
+        let (query, plan) = self.build_page::<K>(query)?;
+
+        Ok(PaginatedQuery {
+            paginator: self,
+            query,
+            plan,
+        })
+    }
+
+    async fn fetch_page<K: CursorKeys<'a, DB>>(
+        &mut self,
+        conn: &mut DB::Connection,
+        query: QueryBuilder<'a, DB>,
+    ) -> Result<PaginationResponse<T>, PaginationError> {
+        let (query, plan) = self.build_page::<K>(query)?;
+        self.fetch_planned(conn, query, plan, |row| row).await
+    }
+}
+
+impl<'a, T, DB: Backend> Paginator<T, DB> {
+    /// Runs a page query built by [`Paginator::build_page`] and turns its rows,
+    /// fetched as `R` and converted with `from_row`, into a response.
+    async fn fetch_planned<R>(
+        &self,
+        conn: &mut DB::Connection,
+        mut query: QueryBuilder<'a, DB>,
+        plan: PagePlan,
+        from_row: fn(R) -> T,
+    ) -> Result<PaginationResponse<T>, PaginationError>
+    where
+        R: for<'r> FromRow<'r, DB::Row> + Send + Unpin + 'static,
+    {
+        #[cfg(feature = "log")]
+        log::debug!("pagination query: {}", query.sql());
+
+        let fetch = async {
+            if self.window_count_ {
+                let rows = match DB::fetch_all_counted::<R>(&mut query, conn).await {
+                    Ok(rows) => rows,
+                    Err(err) => {
+                        return Err(self.fetch_error(err));
+                    }
+                };
+
+                let remaining = rows.first().map_or(0, |(_, count)| {
+                    (*count as u64).saturating_sub(plan.offset + plan.limit as u64)
+                });
+                let rows = rows.into_iter().map(|(row, _)| from_row(row)).collect();
+                // a limit of 0 still reads a row for its count
+                let (data, _) = finalize_page(rows, plan.limit as usize);
+                return self.finish_page(data, None, remaining, &plan);
+            }
+
+            let data: Vec<T> = match DB::fetch_all::<R>(&mut query, conn).await {
+                Ok(rows) => rows.into_iter().map(from_row).collect(),
+                Err(err) => {
+                    return Err(self.fetch_error(err));
+                }
+            };
+
+            let remaining = data.len().saturating_sub(plan.limit as usize) as u64;
+            let (data, overshoot) = finalize_page(data, plan.limit as usize);
+            self.finish_page(data, overshoot, remaining, &plan)
+        };
+
+        #[cfg(feature = "tracing")]
+        let fetch = tracing::Instrument::instrument(fetch, self.page_span(&plan));
+
+        fetch.await
+    }
+
+    /// The span a page is fetched in, with an event for the fetched page. The cursor
+    /// and key values are left out, they may be personal data.
+    #[cfg(feature = "tracing")]
+    fn page_span(&self, plan: &PagePlan) -> tracing::Span {
+        tracing::debug_span!(
+            "paginate",
+            limit = plan.limit,
+            sort = self.cursor_.sort.as_deref().unwrap_or_default(),
+            has_cursor = plan.has_cursor,
+            backward = plan.backward,
+            page = plan.offset_page,
+        )
+    }
+
+    /// Returns the SQL that paginating `query` with [`Paginator::paginate_keys`]
+    /// would run, with the cursor, ORDER BY and LIMIT clauses appended, without
+    /// running it.
+    pub fn dry_run<K: CursorKeys<'a, DB>>(
+        mut self,
+        query: QueryBuilder<'a, DB>,
+    ) -> Result<String, PaginationError> {
+        self.check_query_builder()?;
+        Ok(self.build_page::<K>(query)?.0.sql().to_string())
+    }
+
+    /// Decodes `cursor` the way paginating would, without running any query, e.g. to
+    /// reject a malformed cursor with a 400 in a middleware before the handler runs.
+    /// Checks the cursor's signature, encryption and sort against the paginator's,
+    /// and that it has a value for every key. The values still have to parse as the
+    /// key types when paginating.
+    ///
+    /// Returns the key values, `None` for NULL. Unsigned cursors can also be decoded
+    /// without a paginator with [`decode_cursor`].
+    pub fn validate_cursor(&self, cursor: &str) -> Result<Vec<Option<String>>, PaginationError> {
+        // a self-describing cursor is continued with its own sort, as paginating does
+        let mut paginator = self.clone();
+        if paginator.self_describing_ {
+            paginator.request_.cursor = Some(cursor.to_string());
+            paginator.continue_cursor_request()?;
+        }
+        let keys = paginator.sort_keys()?;
+        let directions = paginator.key_directions(keys.len());
+
+        let mut config = paginator.cursor_;
+        config.sort = Some(cursor_sort(&keys, &directions));
+        config.decode(cursor.to_string(), keys.len())
+    }
+
+    /// The cursor pointing at `row`, as paginating would issue it for a row of a
+    /// page, e.g. to share a link to the position of a row fetched elsewhere. The
+    /// keys are retrieved with [`Paginator::retrieve_keys`], and the cursor is
+    /// issued under the request's sort.
+    pub fn cursor_for(&self, row: &T) -> Result<String, PaginationError> {
+        let retrieve_keys = match &self.retrieve_keys_ {
+            Some(retrieve_keys) => retrieve_keys,
+            None => {
+                return Err(PaginationError::Configuration(String::from(
+                    "paginator has no retrieve_keys configured to build cursors from",
+                )));
+            }
+        };
+
+        let mut paginator = self.clone();
+        if paginator.self_describing_ {
+            paginator.continue_cursor_request()?;
+        }
+        let keys = paginator.sort_keys()?;
+        let directions = paginator.key_directions(keys.len());
+
+        let mut config = paginator.cursor_;
+        config.sort = Some(cursor_sort(&keys, &directions));
+        if paginator.self_describing_ {
+            config.limit = Some(paginator.applied_limit());
+        }
+        config.encode(self.normalized(retrieve_keys(row)))
+    }
+
+    /// The payload of `cursor` before base64, for transports that carry raw bytes,
+    /// e.g. a protobuf `bytes` field that is base64 encoded in JSON already. The
+    /// cursors of responses stay base64 strings.
+    pub fn cursor_to_bytes(&self, cursor: &str) -> Result<Vec<u8>, PaginationError> {
+        self.cursor_.to_bytes(cursor)
+    }
+
+    /// The cursor to set on [`PaginationRequest::cursor`] for a payload from
+    /// [`Paginator::cursor_to_bytes`].
+    pub fn cursor_from_bytes(&self, bytes: &[u8]) -> String {
+        self.cursor_.from_bytes(bytes)
+    }
+
+    /// Whether [`Paginator::validate_cursor`] accepts `cursor`.
+    pub fn cursor_is_valid(&self, cursor: &str) -> bool {
+        self.validate_cursor(cursor).is_ok()
+    }
+
+    /// Counts the rows matching the base query pushed by `base_query`, without
+    /// fetching any, e.g. for a dashboard widget. Unlike
+    /// [`Paginator::with_total_count`] this ignores the request's cursor, limit and
+    /// sort altogether.
+    pub async fn count<F>(
+        &self,
+        db: impl Acquire<'_, Database = DB>,
+        base_query: F,
+    ) -> Result<u64, PaginationError>
+    where
+        F: Fn(&mut QueryBuilder<'a, DB>),
+    {
+        let mut count = self.count_query(&base_query);
+        let mut conn = acquire(db).await?;
+        fetch_total(&mut count, &mut conn).await
+    }
+
+    /// Tells a key column missing from the base query's selection apart from other
+    /// database errors: the keys of the next cursor are retrieved from the fetched
+    /// rows, so the base query must select every key column.
+    fn fetch_error(&self, err: sqlx::Error) -> PaginationError {
+        if let sqlx::Error::ColumnNotFound(column) = &err {
+            let is_key = self.keys_.iter().chain(&self.request_.sort_by).any(|key| {
+                key == column || key.rsplit_once('.').is_some_and(|(_, name)| name == column)
+            });
+
+            if is_key {
+                return PaginationError::Configuration(format!(
+                    "key column {} is not selected by the base query",
+                    column
+                ));
+            }
+        }
+
+        PaginationError::Database(err)
+    }
+
+    /// Wraps the filtered base query in a count of its rows.
+    fn count_query<F>(&self, base_query: &F) -> QueryBuilder<'a, DB>
+    where
+        F: Fn(&mut QueryBuilder<'a, DB>),
+    {
+        count_query(|query| {
+            self.push_base_query(query, base_query);
+            self.push_filters(query);
+            self.push_after_cursor_filters(query, false);
+        })
+    }
+
+    /// Pushes the base query into a new page query, in the window counting the
+    /// rows with [`Paginator::detect_next_via_window`].
+    fn base_page_query<F>(&self, base_query: &F) -> QueryBuilder<'a, DB>
+    where
+        F: Fn(&mut QueryBuilder<'a, DB>),
+    {
+        let mut query = QueryBuilder::new("");
+        if self.window_count_ {
+            query.push(format!(
+                "SELECT page.*, COUNT(*) OVER () AS {} FROM (",
+                backend::WINDOW_COUNT_COLUMN
+            ));
+        }
+        self.push_base_query(&mut query, base_query);
+        query
+    }
+
+    /// Pushes the base query, as a subquery with [`Paginator::wrap_subquery`].
+    fn push_base_query<F>(&self, query: &mut QueryBuilder<'a, DB>, base_query: &F)
+    where
+        F: Fn(&mut QueryBuilder<'a, DB>),
+    {
+        if self.wrap_subquery_ {
+            query.push("SELECT * FROM (");
+            base_query(query);
+            query.push(") AS sub");
+        } else {
+            base_query(query);
+        }
+    }
+
+    /// Encodes the cursor of a fetched row, checking with
+    /// [`Paginator::validate_cursor_roundtrip`] that its keys parse back with
+    /// `parse_keys`.
+    fn row_cursor(
+        &self,
+        retrieve_keys: &RetrieveKeysFn<T>,
+        row: &T,
+        parse_keys: ParseKeysFn,
+    ) -> Result<String, PaginationError> {
+        let keys = self.normalized(retrieve_keys(row));
+        if self.check_cursor_keys_ {
+            if let Err(err) = parse_keys(keys.clone()) {
+                return Err(PaginationError::Configuration(format!(
+                    "retrieved cursor keys don't parse back as the key types ({}), \
+                     format them with KeyParse::format_key",
+                    err
+                )));
+            }
+        }
+        self.cursor_.encode(keys)
+    }
+
+    /// Applies [`Paginator::normalize_keys`] to the keys retrieved from a row.
+    fn normalized(&self, keys: Vec<Option<String>>) -> Vec<Option<String>> {
+        match &self.normalize_keys_ {
+            Some(normalize_keys) => normalize_keys(keys),
+            None => keys,
+        }
+    }
+
+    /// Whether the cursor predicate and filters are joined to a `WHERE` clause of
+    /// the base query, rather than to the subquery it's wrapped in.
+    fn has_predicate(&self) -> bool {
+        self.base_predicate_ && !self.wrap_subquery_
+    }
+
+    /// Joins the [`Paginator::filter`]s to the base query.
+    fn push_filters(&self, query: &mut QueryBuilder<'_, DB>) {
+        push_predicates(query, &self.filters_, self.has_predicate());
+    }
+
+    /// Joins the [`Paginator::filter_after_cursor`]s after the other filters and the
+    /// cursor predicate, if any.
+    fn push_after_cursor_filters(&self, query: &mut QueryBuilder<'_, DB>, has_cursor: bool) {
+        let preceded = self.has_predicate() || !self.filters_.is_empty() || has_cursor;
+        push_predicates(query, &self.after_cursor_filters_, preceded);
+    }
+
+    /// Fails for the options that rebuild the base query, which a prebuilt
+    /// [`QueryBuilder`] can't be.
+    fn check_query_builder(&self) -> Result<(), PaginationError> {
+        if self.total_count_ {
+            return Err(PaginationError::Configuration(String::from(
+                "with_total_count requires paginate_keys_with to rebuild the base query",
+            )));
+        }
+
+        if self.window_count_ {
+            return Err(PaginationError::Configuration(String::from(
+                "detect_next_via_window requires paginate_keys_with to wrap the base query",
+            )));
+        }
+
+        if self.wrap_subquery_ {
+            return Err(PaginationError::Configuration(String::from(
+                "wrap_subquery requires paginate_keys_with to wrap the base query",
+            )));
+        }
+
+        Ok(())
+    }
+
+    fn build_page<K: CursorKeys<'a, DB>>(
+        &mut self,
+        mut query: QueryBuilder<'a, DB>,
+    ) -> Result<(QueryBuilder<'a, DB>, PagePlan), PaginationError> {
+        if self.keys_.len() != K::LEN {
+            return Err(PaginationError::Configuration(format!(
+                "paginator configured with {} keys but paginated with {}",
+                self.keys_.len(),
+                K::LEN
+            )));
+        }
+
+        check_base_query(query.sql())?;
+
+        if self.nulls_.is_some() && !DB::SUPPORTS_NULLS_ORDER {
+            return Err(PaginationError::Configuration(String::from(
+                "nulls needs NULLS FIRST/LAST, which the database doesn't support",
+            )));
+        }
+
+        // without retrieve_keys every cursor would come out empty
+        if self.retrieve_keys_.is_none() && !self.offset_mode_ {
+            return Err(PaginationError::Configuration(String::from(
+                "paginator has no retrieve_keys configured to build cursors from",
+            )));
+        }
+
+        if self.self_describing_ && !self.offset_mode_ {
+            self.continue_cursor_request()?;
+        }
+
+        let keys = self.sort_keys()?;
+
+        let mut offset_page = None;
+        if self.offset_mode_ {
+            offset_page = Some(self.request_.page.unwrap_or(1).max(1));
+        }
+
+        let directions = self.key_directions(keys.len());
+        self.cursor_.sort = Some(cursor_sort(&keys, &directions));
+
+        let sort_order = match directions.first() {
+            Some(&descending) if directions.iter().all(|key| *key == descending) => {
+                Some(if descending {
+                    SortOrder::Desc
+                } else {
+                    SortOrder::Asc
+                })
+            }
+            _ => None,
+        };
+
+        let mut cursor_keys: Option<(K, Vec<bool>)> = None;
+        let mut seeking = false;
+        match (offset_page, self.request_.cursor.clone(), &self.seek_) {
+            (None, Some(cursor), _) => {
+                let values = self.cursor_.decode(cursor, K::LEN)?;
+                let nulls = values.iter().map(Option::is_none).collect();
+                cursor_keys = Some((K::parse_keys(values)?, nulls));
+            }
+            (None, None, Some(seek)) => {
+                if seek.len() != K::LEN {
+                    return Err(PaginationError::Configuration(format!(
+                        "paginator seeks to {} keys but paginated with {}",
+                        seek.len(),
+                        K::LEN
+                    )));
+                }
+
+                let values = seek.iter().cloned().map(Some).collect();
+                cursor_keys = Some((K::parse_keys(values)?, vec![false; K::LEN]));
+                seeking = true;
+            }
+            _ => {}
+        }
+
+        // paging backward only makes sense relative to a cursor
+        let backward = cursor_keys.is_some()
+            && !seeking
+            && self.request_.direction.clone() == Some(PageDirection::Backward);
+
+        let limit = self.applied_limit();
+        if self.self_describing_ {
+            self.cursor_.limit = Some(limit);
+        }
+
+        // an empty page resumes from the request's cursor, which a first page lacks
+        if limit == 0 && offset_page.is_none() && self.request_.cursor.is_none() {
+            return Err(PaginationError::InvalidArgument(
+                "limit 0 requires a cursor",
+            ));
+        }
+
+        let mut keyset = Keyset::new(
+            keys.into_iter()
+                .zip(directions)
+                .map(|(key, descending)| {
+                    let expression = self
+                        .key_expressions_
+                        .iter()
+                        .chain(&self.key_columns_)
+                        .find(|(alias, _)| *alias == key)
+                        .map(|(_, expression)| expression.clone());
+                    let collation = self
+                        .key_collations_
+                        .iter()
+                        .find(|(collated, _)| *collated == key)
+                        .map(|(_, collation)| collation.clone());
+
+                    match expression {
+                        Some(expression) => KeyColumn {
+                            name: expression,
+                            alias: Some(key),
+                            descending,
+                            nulls: self.nulls_.clone(),
+                            collation,
+                        },
+                        None => KeyColumn {
+                            name: key,
+                            alias: None,
+                            descending,
+                            nulls: self.nulls_.clone(),
+                            collation,
+                        },
+                    }
+                })
+                .collect(),
+        )
+        .nulls_smallest(DB::NULLS_SMALLEST);
+
+        // when paging backward we walk the keyset in the opposite direction and
+        // reverse the fetched rows afterwards
+        if backward {
+            keyset = keyset.reversed();
+        }
+
+        if seeking || self.inclusive_cursor_ {
+            keyset = keyset.inclusive();
+        }
+
+        self.push_filters(&mut query);
+
+        let has_cursor = cursor_keys.is_some();
+        if let Some((keys, nulls)) = cursor_keys {
+            query.push(if self.has_predicate() || !self.filters_.is_empty() {
+                " AND"
+            } else {
+                " WHERE"
+            });
+            keys.push_where(&keyset, &nulls, &mut query);
+        }
+        self.push_after_cursor_filters(&mut query, has_cursor);
+
+        // we add 1 to limit to ensure there's a next page (the extra record will be discarded),
+        // and the rows of the pages looked ahead over
+        let mut fetch_limit = limit
+            .saturating_mul(self.lookahead_pages_)
+            .saturating_add(1);
+        if self.window_count_ {
+            // the window counts the rows after the cursor instead, but needs a row
+            // to be read from
+            query.push(") AS page");
+            keyset = keyset.unqualified();
+            fetch_limit = limit.max(1);
+        }
+
+        keyset.push_order_by(&mut query);
+        keyset.push_limit(&mut query, fetch_limit);
+
+        let mut offset = 0;
+        if let Some(page) = offset_page {
+            offset = (page as u64 - 1) * limit as u64;
+            keyset.push_offset(&mut query, offset);
+        }
+
+        let plan = PagePlan {
+            limit,
+            sort_order,
+            offset,
+            offset_page,
+            has_cursor,
+            backward,
+            parse_keys: |values| K::parse_keys(values).map(drop),
+        };
+
+        Ok((query, plan))
+    }
+
+    /// Returns for each of the `len` keys whether it's sorted descending.
+    fn key_directions(&self, len: usize) -> Vec<bool> {
+        if !self.key_orders_.is_empty() {
+            return self
+                .key_orders_
+                .iter()
+                .map(|order| *order == SortOrder::Desc)
+                .collect();
+        }
+
+        let mut descending = true;
+        if let Some(o) = self.request_.sort_order.clone() {
+            descending = o == SortOrder::Desc;
+        }
+        vec![descending; len]
+    }
+
+    /// The page size of the request, defaulted and clamped.
+    fn applied_limit(&self) -> u32 {
+        page_limit(
+            self.limit_.or(self.request_.limit),
+            self.default_limit_,
+            self.max_limit_,
+        )
+    }
+
+    /// Defaults the limit, `sort_by` and sort order of the request to those of its
+    /// cursor, for [`Paginator::self_describing_cursors`].
+    fn continue_cursor_request(&mut self) -> Result<(), PaginationError> {
+        let cursor = match &self.request_.cursor {
+            Some(cursor) => cursor,
+            None => return Ok(()),
+        };
+        let payload = self.cursor_.decode_unsorted(cursor)?;
+
+        if self.request_.limit.is_none() {
+            self.request_.limit = payload.limit;
+        }
+
+        // the sort of the first key, e.g. `created_at desc` of `created_at desc,id desc`
+        let first = payload
+            .sort
+            .as_deref()
+            .and_then(|sort| sort.split(',').next());
+        if let Some((key, order)) = first.and_then(|column| column.split_once(' ')) {
+            if self.request_.sort_by.is_none()
+                && self.allowed_sort_columns_.iter().any(|c| c == key)
+            {
+                self.request_.sort_by = Some(key.to_string());
+            }
+            if self.request_.sort_order.is_none() {
+                self.request_.sort_order = order.parse().ok();
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Returns the keys to paginate on, with the first one replaced by the requested
+    /// `sort_by` column if it's allowed.
+    ///
+    /// The keys are interpolated into the query as raw identifiers, so each of them
+    /// is validated with [`validate_identifier`], which also rejects empty keys. A key
+    /// can't be repeated, e.g. by a `sort_by` of the last key.
+    fn sort_keys(&self) -> Result<Vec<String>, PaginationError> {
+        let mut keys = self.keys_.clone();
+
+        if !self.allowed_sort_columns_.is_empty() {
+            if let Some(sort_by) = &self.request_.sort_by {
+                if !self.allowed_sort_columns_.contains(sort_by) {
+                    return Err(PaginationError::InvalidArgument("invalid sort_by"));
+                }
+
+                if let Some(key) = keys.first_mut() {
+                    *key = sort_by.clone();
+                }
+            }
+        }
+
+        for (index, key) in keys.iter().enumerate() {
+            validate_identifier(key)?;
+
+            // a repeated key adds nothing to the order, and makes a degenerate
+            // cursor predicate
+            if keys[..index].contains(key) {
+                return Err(PaginationError::InvalidArgument("duplicate key"));
+            }
+        }
+        for (_, column) in &self.key_columns_ {
+            validate_identifier(column)?;
+        }
+        // collations are quoted into the query, so they can't contain quotes
+        for (_, collation) in &self.key_collations_ {
+            if collation.is_empty()
+                || !collation
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | '-' | '.' | '@'))
+            {
+                return Err(PaginationError::InvalidArgument("invalid key collation"));
+            }
+        }
+
+        Ok(keys)
+    }
+
+    /// Turns the rows of a page and the overshoot row, if fetched, into a response.
+    fn finish_page(
+        &self,
+        data: Vec<T>,
+        overshoot: Option<T>,
+        remaining: u64,
+        plan: &PagePlan,
+    ) -> Result<PaginationResponse<T>, PaginationError> {
+        let has_more = remaining > 0;
+
+        #[cfg(feature = "tracing")]
+        tracing::debug!(rows = data.len(), has_more, "fetched page");
+
+        // the next page starts strictly after the last row's keys
+        #[cfg(feature = "log")]
+        if let (true, Some(retrieve_keys), Some(last), Some(overshoot)) = (
+            cfg!(debug_assertions),
+            &self.retrieve_keys_,
+            data.last(),
+            &overshoot,
+        ) {
+            if self.normalized(retrieve_keys(last)) == self.normalized(retrieve_keys(overshoot)) {
+                log::warn!(
+                    "rows share the cursor keys {:?} across a page boundary and would be \
+                     skipped, the last key should be unique",
+                    self.keys_
+                );
+            }
+        }
+
+        let peeked_row = overshoot.filter(|_| self.peek_row_);
+
+        let mut res: PaginationResponse<T> = PaginationResponse {
+            data,
+            next_cursor: None,
+            prev_cursor: None,
+            total: None,
+            has_next_page: false,
+            has_prev_page: false,
+            page: plan.offset_page,
+            total_pages: None,
+            applied_limit: plan.limit,
+            applied_sort_order: plan.sort_order.clone(),
+            cursors: vec![],
+            pages_remaining_at_least: pages_remaining(remaining, plan.limit, self.lookahead_pages_),
+            peeked_row,
+        };
+
+        // the cursors are retrieved from the rows in the sort order
+        if plan.backward {
+            res.data.reverse();
+        }
+
+        if let Some(page) = plan.offset_page {
+            res.has_next_page = has_more;
+            res.has_prev_page = page > 1;
+            return Ok(res);
+        }
+
+        let (has_next, has_prev) = page_bounds(has_more, plan.has_cursor, plan.backward);
+        res.has_next_page = has_next;
+        res.has_prev_page = has_prev;
+
+        // an empty page (e.g. limit 0) stays where the request's cursor was
+        if res.data.is_empty() {
+            if has_next {
+                res.next_cursor = self.request_.cursor.clone();
+            }
+            if has_prev {
+                res.prev_cursor = self.request_.cursor.clone();
+            }
+        }
+
+        if let Some(retrieve_keys) = &self.retrieve_keys_ {
+            let cursor = |row: &T| self.row_cursor(retrieve_keys, row, plan.parse_keys);
+
+            if self.row_cursors_ {
+                for row in &res.data {
+                    res.cursors.push(cursor(row)?);
+                }
+            }
+
+            if has_next {
+                if let Some(last) = res.data.last() {
+                    res.next_cursor = Some(cursor(last)?);
+                }
+            }
+
+            if has_prev {
+                if let Some(first) = res.data.first() {
+                    res.prev_cursor = Some(cursor(first)?);
+                }
+            }
+        }
+
+        if plan.backward && !self.display_order_ {
+            res.data.reverse();
+            res.cursors.reverse();
+        }
+
+        Ok(res)
+    }
+
+    #[cfg(feature = "indexed")]
+    fn to_indexed(&self, res: PaginationResponse<T>) -> Result<IndexedPage<T>, PaginationError> {
+        let retrieve_keys = match &self.retrieve_keys_ {
+            Some(retrieve_keys) => retrieve_keys,
+            None => {
+                return Err(PaginationError::Configuration(String::from(
+                    "paginate_indexed requires retrieve_keys to key the rows",
+                )));
+            }
+        };
+
+        let mut data = indexmap::IndexMap::with_capacity(res.data.len());
+        for row in res.data {
+            let key = match self.normalized(retrieve_keys(&row)).into_iter().next() {
+                Some(Some(key)) => key,
+                _ => {
+                    return Err(PaginationError::Configuration(String::from(
+                        "paginate_indexed requires a first key that is never NULL",
+                    )));
+                }
+            };
+
+            if data.insert(key, row).is_some() {
+                return Err(PaginationError::Configuration(String::from(
+                    "first key repeats within a page, paginate_indexed requires it to be unique",
+                )));
+            }
+        }
+
+        Ok(IndexedPage {
+            data,
+            next_cursor: res.next_cursor,
+            prev_cursor: res.prev_cursor,
+            has_next_page: res.has_next_page,
+            has_prev_page: res.has_prev_page,
+        })
+    }
+
+    #[cfg(feature = "relay")]
+    fn to_connection(
+        &self,
+        res: PaginationResponse<T>,
+        parse_keys: ParseKeysFn,
+    ) -> Result<Connection<T>, PaginationError> {
+        let retrieve_keys = match &self.retrieve_keys_ {
+            Some(retrieve_keys) => retrieve_keys,
+            None => {
+                return Err(PaginationError::Configuration(String::from(
+                    "paginate_connection requires retrieve_keys to build edge cursors",
+                )));
+            }
+        };
+
+        let mut edges = Vec::with_capacity(res.data.len());
+        for node in res.data {
+            let cursor = self.row_cursor(retrieve_keys, &node, parse_keys)?;
+            edges.push(Edge { node, cursor });
+        }
+
+        let page_info = PageInfo {
+            has_next_page: res.has_next_page,
+            has_previous_page: res.has_prev_page,
+            start_cursor: edges.first().map(|edge| edge.cursor.clone()),
+            end_cursor: edges.last().map(|edge| edge.cursor.clone()),
+        };
+
+        Ok(Connection { edges, page_info })
+    }
+}
+
+impl<'a, S, DB> Paginator<S, DB>
+where
+    DB: Backend,
+    S: KeyParse + for<'r> Decode<'r, DB> + Type<DB> + Send + Sync + Unpin + 'static,
+    usize: ColumnIndex<DB::Row>,
+{
+    /// Paginates a query selecting a single column (e.g. `SELECT id FROM users`)
+    /// into its values, without a [`FromRow`] struct. The column is the key, so
+    /// unless [`Paginator::retrieve_key`] says otherwise the cursors are built from
+    /// the values with [`KeyParse::format_key`].
+    pub async fn paginate_scalar(
+        mut self,
+        db: impl Acquire<'_, Database = DB>,
+        query: QueryBuilder<'a, DB>,
+    ) -> Result<PaginationResponse<S>, PaginationError>
+    where
+        (S,): CursorKeys<'a, DB>,
+    {
+        self.check_query_builder()?;
+
+        if self.retrieve_keys_.is_none() {
+            self.retrieve_keys_ = Some(Arc::new(|value: &S| vec![value.format_nullable_key()]));
+        }
+
+        let (query, plan) = self.build_page::<(S,)>(query)?;
+        let mut conn = acquire(db).await?;
+        self.fetch_planned(&mut conn, query, plan, |(value,): (S,)| value)
+            .await
+    }
+}
+
+/// A page query built by [`Paginator::build`].
+pub struct PaginatedQuery<'a, T, DB: Database = Postgres> {
+    paginator: Paginator<T, DB>,
+    query: QueryBuilder<'a, DB>,
+    plan: PagePlan,
+}
+
+impl<'a, T, DB> PaginatedQuery<'a, T, DB>
+where
+    DB: Backend,
+    T: for<'r> FromRow<'r, DB::Row> + Send + Sync + Unpin + 'static,
+{
+    /// The SQL of the page query, with the cursor values as placeholders.
+    pub fn sql(&self) -> &str {
+        self.query.sql()
+    }
+
+    /// Runs the page query on `db`, e.g. `&pool`, `&mut *transaction` or
+    /// `&mut connection`, and builds the response's cursors from its rows.
+    pub async fn execute<'c, A>(self, db: A) -> Result<PaginationResponse<T>, PaginationError>
+    where
+        A: Acquire<'c, Database = DB>,
+    {
+        let mut conn = acquire(db).await?;
+        self.paginator
+            .fetch_planned(&mut conn, self.query, self.plan, |row| row)
+            .await
+    }
+}
+
+/// Joins `filters` to a query with `AND`, or with `WHERE` for the first one unless
+/// other predicates precede them.
+fn push_predicates<DB: Database>(
+    query: &mut QueryBuilder<'_, DB>,
+    filters: &[FilterFn<DB>],
+    preceded: bool,
+) {
+    for (index, filter) in filters.iter().enumerate() {
+        query.push(if preceded || index > 0 {
+            " AND ("
+        } else {
+            " WHERE ("
+        });
+        filter(query);
+        query.push(")");
+    }
+}
+
+/// Acquires a connection from `db` to run the queries of a page on, e.g. one of a
+/// pool, or the connection of a transaction.
+async fn acquire<'c, A: Acquire<'c>>(db: A) -> Result<A::Connection, PaginationError> {
+    match db.acquire().await {
+        Ok(conn) => Ok(conn),
+        Err(err) => Err(PaginationError::Database(err)),
+    }
+}
+
+/// Runs a count query built by [`Paginator::count_query`].
+async fn fetch_total<DB: Backend>(
+    query: &mut QueryBuilder<'_, DB>,
+    conn: &mut DB::Connection,
+) -> Result<u64, PaginationError> {
+    #[cfg(feature = "log")]
+    log::debug!("pagination count query: {}", query.sql());
+
+    match DB::fetch_count(query, conn).await {
+        Ok(count) => Ok(count as u64),
+        Err(err) => Err(PaginationError::Database(err)),
+    }
+}
+
+/// Splits the rows of a page query fetching more than `limit` rows into the page and
+/// the overshoot row, which is only there when there are more rows in the walked
+/// direction. The rows after the overshoot row are dropped.
+fn finalize_page<T>(mut rows: Vec<T>, limit: usize) -> (Vec<T>, Option<T>) {
+    if rows.len() > limit {
+        let overshoot = rows.swap_remove(limit);
+        rows.truncate(limit);
+        return (rows, Some(overshoot));
+    }
+
+    (rows, None)
+}
+
+/// The pages of `limit` rows that `remaining` rows after the page fill at least
+/// partly, up to `lookahead`.
+fn pages_remaining(remaining: u64, limit: u32, lookahead: u32) -> usize {
+    let pages = match limit {
+        // a limit of 0 only probes for a next row
+        0 => remaining.min(1),
+        limit => remaining.div_ceil(limit as u64),
+    };
+    pages.min(lookahead as u64) as usize
+}
+
+/// What's needed to turn the rows of a page query into a response.
+struct PagePlan {
+    limit: u32,
+    sort_order: Option<SortOrder>,
+    offset: u64,
+    offset_page: Option<u32>,
+    has_cursor: bool,
+    backward: bool,
+    /// Parses the keys retrieved from a row as the key types, to check them.
+    parse_keys: ParseKeysFn,
+}
+
+/// Checks that the base query has no ORDER BY, LIMIT or OFFSET of its own, which
+/// the appended ones would clash with. Only the clauses at the nesting depth the
+/// query ends at count, not those of subqueries, function calls, literals or
+/// comments.
+///
+/// It's a tokenizer, not a parser: literals end at the next matching quote, so a
+/// backslash-escaped one (Postgres `E'it\'s'`, MySQL's default strings) ends them
+/// early; MySQL's `#` comments aren't skipped; and block comments are taken to nest
+/// as on Postgres. Past any of these the depth and words may be off, missing a
+/// clause or flagging one that's quoted.
+fn check_base_query(sql: &str) -> Result<(), PaginationError> {
+    let mut depth: usize = 0;
+    let mut words: Vec<(usize, String)> = vec![];
+    let mut word = String::new();
+
+    let mut chars = sql.char_indices().peekable();
+    while let Some((index, c)) = chars.next() {
+        if c.is_alphanumeric() || c == '_' {
+            word.push(c.to_ascii_uppercase());
+            continue;
+        }
+        if !word.is_empty() {
+            words.push((depth, std::mem::take(&mut word)));
+        }
+
+        match c {
+            '(' => depth += 1,
+            ')' => depth = depth.saturating_sub(1),
+            // a doubled quote escapes it, which closes and reopens the literal
+            '\'' | '"' | '`' => {
+                chars.find(|(_, next)| *next == c);
+            }
+            '-' if chars.peek().is_some_and(|(_, next)| *next == '-') => {
+                chars.find(|(_, next)| *next == '\n');
+            }
+            // block comments nest on Postgres
+            '/' if chars.peek().is_some_and(|(_, next)| *next == '*') => {
+                chars.next();
+                let mut nesting = 1;
+                while let Some((_, next)) = chars.next() {
+                    let following = chars.peek().map(|(_, following)| *following);
+                    match (next, following) {
+                        ('*', Some('/')) => nesting -= 1,
+                        ('/', Some('*')) => nesting += 1,
+                        _ => continue,
+                    }
+                    chars.next();
+                    if nesting == 0 {
+                        break;
+                    }
+                }
+            }
+            // a dollar-quoted string, `$$...$$` or `$tag$...$tag$`, rather than a
+            // placeholder like `$1`
+            '$' => {
+                let rest = &sql[index + 1..];
+                let tag_len = rest
+                    .find(|next: char| !(next.is_alphanumeric() || next == '_'))
+                    .unwrap_or(rest.len());
+                let tag = &rest[..tag_len];
+                if rest[tag_len..].starts_with('$')
+                    && !tag.starts_with(|first: char| first.is_ascii_digit())
+                {
+                    let delimiter = format!("${}$", tag);
+                    let start = index + delimiter.len();
+                    let end = match sql[start..].find(&delimiter) {
+                        Some(offset) => start + offset + delimiter.len(),
+                        None => sql.len(),
+                    };
+                    while chars.next_if(|(next, _)| *next < end).is_some() {}
+                }
+            }
+            _ => {}
+        }
+    }
+    if !word.is_empty() {
+        words.push((depth, word));
+    }
+
+    let clauses: Vec<&str> = words
+        .iter()
+        .filter(|(word_depth, _)| *word_depth == depth)
+        .map(|(_, word)| word.as_str())
+        .collect();
+    if clauses.windows(2).any(|pair| pair == ["ORDER", "BY"])
+        || clauses
+            .iter()
+            .any(|word| *word == "LIMIT" || *word == "OFFSET")
+    {
+        return Err(PaginationError::Configuration(String::from(
+            "base query has its own ORDER BY, LIMIT or OFFSET, the paginator appends them",
+        )));
+    }
+
+    Ok(())
+}
+
+/// Checks that `identifier` is a plain SQL identifier (`[A-Za-z_][A-Za-z0-9_]*`),
+/// optionally qualified as `table.column` or `schema.table.column`.
+fn validate_identifier(identifier: &str) -> Result<(), PaginationError> {
+    let parts: Vec<&str> = identifier.split('.').collect();
+    if parts.len() > 3 {
+        return Err(PaginationError::InvalidArgument("invalid key identifier"));
+    }
+
+    for part in parts {
+        let mut chars = part.chars();
+        let valid = match chars.next() {
+            Some(c) if c.is_ascii_alphabetic() || c == '_' => {
+                chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+            }
+            _ => false,
+        };
+
+        if !valid {
+            return Err(PaginationError::InvalidArgument("invalid key identifier"));
+        }
+    }
+
+    Ok(())
+}
+
+/// Largest page size whatever [`Paginator::max_limit`] is set to, so that a huge
+/// configured limit can't make a single query read a whole table.
+const MAX_PAGE_LIMIT: u32 = 100_000;
+
+fn page_limit(requested: Option<u32>, default_limit: u32, max_limit: u32) -> u32 {
+    let max_limit = max_limit.min(MAX_PAGE_LIMIT);
+    match requested {
+        Some(limit) => limit.min(max_limit),
+        None => default_limit.min(max_limit),
+    }
+}
+
+/// Describes the sort cursors are issued under, e.g. `priority asc,id desc`, so
+/// that a cursor can't be reused under another sort.
+fn cursor_sort(keys: &[String], directions: &[bool]) -> String {
+    let columns: Vec<String> = keys
+        .iter()
+        .zip(directions)
+        .map(|(key, descending)| format!("{} {}", key, if *descending { "desc" } else { "asc" }))
+        .collect();
+    columns.join(",")
+}
+
+fn count_query<'a, DB: Database>(
+    base_query: impl Fn(&mut QueryBuilder<'a, DB>),
+) -> QueryBuilder<'a, DB> {
+    let mut count = QueryBuilder::new("SELECT COUNT(*) FROM (");
+    base_query(&mut count);
+    count.push(") AS base");
+    count
+}
+
+/// Returns whether the page has a next and a previous page.
+///
+/// Walking forward, a previous page exists whenever we started from a cursor.
+/// Walking backward we always came from a later page, so a next page exists,
+/// and a previous page exists only if the overshoot row was fetched. In
+/// particular, paging backward onto the first page yields no previous page.
+fn page_bounds(has_more: bool, has_cursor: bool, backward: bool) -> (bool, bool) {
+    if backward {
+        (true, has_more)
+    } else {
+        (has_more, has_cursor)
+    }
+}
+
+/// A tuple of typed cursor keys, parsed from the decoded cursor values and
+/// bound into the keyset WHERE clause.
+pub trait CursorKeys<'a, DB: Database>: Sized {
+    const LEN: usize;
+
+    fn parse_keys(values: Vec<Option<String>>) -> Result<Self, PaginationError>;
+
+    fn push_where(self, keyset: &Keyset, nulls: &[bool], query: &mut QueryBuilder<'a, DB>);
+}
+
+/// A tuple of typed key values retrieved from a row with
+/// [`Paginator::retrieve_typed_keys`], e.g. `(DateTime<Utc>, i64)`.
+pub trait KeyTuple {
+    fn format_keys(&self) -> Vec<Option<String>>;
+}
+
+macro_rules! impl_cursor_keys {
+    ($len:expr, $($key:ident $index:tt),+) => {
+        impl<$($key: KeyParse),+> KeyTuple for ($($key,)+) {
+            fn format_keys(&self) -> Vec<Option<String>> {
+                vec![$(self.$index.format_nullable_key()),+]
+            }
+        }
+
+        impl<'a, DB: Database, $($key),+> CursorKeys<'a, DB> for ($($key,)+)
+        where
+            $(
+                $key: 'a
+                    + KeyParse
+                    + Clone
+                    + sqlx::Encode<'a, DB>
+                    + sqlx::Type<DB>
+                    + Send,
+            )+
+        {
+            const LEN: usize = $len;
+
+            fn parse_keys(values: Vec<Option<String>>) -> Result<Self, PaginationError> {
+                let mut values = values.into_iter();
+                Ok(($($key::parse_nullable_key(values.next().flatten())?,)+))
+            }
+
+            fn push_where(
+                self,
+                keyset: &Keyset,
+                nulls: &[bool],
+                query: &mut QueryBuilder<'a, DB>,
+            ) {
+                keyset.push_where(query, nulls, &mut |query, index| match index {
+                    $($index => {
+                        query.push_bind(self.$index.clone());
+                    })+
+                    _ => {}
+                });
+            }
+        }
+    };
+}
+
+impl_cursor_keys!(1, K1 0);
+impl_cursor_keys!(2, K1 0, K2 1);
+impl_cursor_keys!(3, K1 0, K2 1, K3 2);
+impl_cursor_keys!(4, K1 0, K2 1, K3 2, K4 3);
+
+/// The error of a cursor value that doesn't parse as a `$kind` key. The message is
+/// fixed per key type, so that a client's value is never echoed back into responses
+/// or logs.
+macro_rules! invalid_key {
+    ($kind:literal) => {
+        PaginationError::InvalidCursor(CursorError::InvalidKey(concat!(
+            "cursor key is not a valid ",
+            $kind
+        )))
+    };
+}
+
+/// Implements [`KeyParse`] for types that implement `FromStr` and `Display`, e.g. a
+/// Rust enum sqlx maps to a Postgres enum, or a newtype around a Postgres type.
+/// `Display` must write the format `FromStr` reads back.
+///
+/// ```ignore
+/// #[derive(sqlx::Type)]
+/// #[sqlx(type_name = "priority", rename_all = "lowercase")]
+/// enum Priority {
+///     Low,
+///     High,
+/// }
+///
+/// impl FromStr for Priority { ... }
+/// impl Display for Priority { ... }
+///
+/// sqlx_cursor_paginator::from_str_key!(Priority);
+/// ```
+#[macro_export]
+macro_rules! from_str_key {
+    ($($ty:ty),+ $(,)?) => {
+        $(
+            impl $crate::KeyParse for $ty {
+                fn parse_key(key: String) -> Result<Self, $crate::PaginationError> {
+                    match key.parse::<$ty>() {
+                        Ok(res) => Ok(res),
+                        Err(_) => Err($crate::PaginationError::InvalidCursor(
+                            $crate::CursorError::InvalidKey(concat!(
+                                "cursor key is not a valid ",
+                                stringify!($ty)
+                            )),
+                        )),
+                    }
+                }
+
+                fn format_key(&self) -> String {
+                    self.to_string()
+                }
+            }
+        )+
+    };
+}
+
+/// A key type that can go in a cursor.
+///
+/// Every type has one canonical string format, written by [`KeyParse::format_key`]
+/// and read back by [`KeyParse::parse_key`]. Retrieving keys with `format_key`,
+/// or [`Paginator::retrieve_typed_keys`] which uses it, guarantees the next
+/// request's cursor parses:
+///
+/// - `String`: the value itself
+/// - `i32`, `i64`: decimal digits
+/// - `DateTime<Utc>`: RFC 3339 in UTC with as many fractional digits as needed
+///   (`2024-01-02T03:04:05.678Z`)
+/// - `DateTime<FixedOffset>`: the same as `DateTime<Utc>`, the offset is not kept
+///   in the cursor (`2024-01-02T10:04:05+07:00` becomes `2024-01-02T03:04:05Z`)
+/// - `NaiveDateTime`: `2024-01-02 03:04:05.678`, as `to_string()` writes it
+/// - `uuid::Uuid`: lowercase hyphenated
+/// - `rust_decimal::Decimal`: plain digits keeping the scale (`10.00`)
+pub trait KeyParse: Sized {
+    fn parse_key(key: String) -> Result<Self, PaginationError>;
+
+    fn format_key(&self) -> String;
+
+    /// Formats a key that may be NULL, the counterpart of
+    /// [`KeyParse::parse_nullable_key`].
+    fn format_nullable_key(&self) -> Option<String> {
+        Some(self.format_key())
+    }
+
+    /// Parses a cursor value that may be NULL. Only nullable keys (`Option<K>`)
+    /// accept a NULL.
+    fn parse_nullable_key(key: Option<String>) -> Result<Self, PaginationError> {
+        match key {
+            Some(key) => Self::parse_key(key),
+            None => Err(PaginationError::InvalidCursor(CursorError::NullKey)),
+        }
+    }
+}
+
+impl<K: KeyParse> KeyParse for Option<K> {
+    fn parse_key(key: String) -> Result<Option<K>, PaginationError> {
+        Ok(Some(K::parse_key(key)?))
+    }
+
+    /// Formats NULL as an empty string, use [`KeyParse::format_nullable_key`] to
+    /// keep it NULL in the cursor.
+    fn format_key(&self) -> String {
+        self.format_nullable_key().unwrap_or_default()
+    }
+
+    fn format_nullable_key(&self) -> Option<String> {
+        self.as_ref().map(K::format_key)
+    }
+
+    fn parse_nullable_key(key: Option<String>) -> Result<Option<K>, PaginationError> {
+        match key {
+            Some(key) => Self::parse_key(key),
+            None => Ok(None),
+        }
+    }
+}
+
+impl KeyParse for String {
+    fn parse_key(key: String) -> Result<String, PaginationError> {
+        Ok(key)
+    }
+
+    fn format_key(&self) -> String {
+        self.clone()
+    }
+}
+
+#[cfg(feature = "chrono")]
+impl KeyParse for DateTime<Utc> {
+    fn parse_key(key: String) -> Result<DateTime<Utc>, PaginationError> {
+        let res: DateTime<Utc> = match key.parse::<DateTime<Utc>>() {
+            Ok(res) => res,
+            Err(_) => {
+                return Err(invalid_key!("date time"));
+            }
+        };
+        Ok(res)
+    }
+
+    fn format_key(&self) -> String {
+        self.to_rfc3339_opts(SecondsFormat::AutoSi, true)
+    }
+}
+
+/// Normalizes to UTC, so that the cursor values of one key write the same instant
+/// the same way whatever the offsets of the rows, and still sort the same as text.
+/// Any RFC 3339 offset parses, e.g. from a cursor built by hand.
+#[cfg(feature = "chrono")]
+impl KeyParse for DateTime<FixedOffset> {
+    fn parse_key(key: String) -> Result<DateTime<FixedOffset>, PaginationError> {
+        let res: DateTime<FixedOffset> = match DateTime::parse_from_rfc3339(&key) {
+            Ok(res) => res,
+            Err(_) => {
+                return Err(invalid_key!("date time"));
+            }
+        };
+        Ok(res)
+    }
+
+    fn format_key(&self) -> String {
+        self.with_timezone(&Utc).format_key()
+    }
+}
+
+/// The format of `NaiveDateTime::to_string()`, the fraction is only written when
+/// non-zero.
+#[cfg(feature = "chrono")]
+const NAIVE_DATE_TIME_FORMAT: &str = "%Y-%m-%d %H:%M:%S%.f";
+
+#[cfg(feature = "chrono")]
+impl KeyParse for NaiveDateTime {
+    fn parse_key(key: String) -> Result<NaiveDateTime, PaginationError> {
+        let res: NaiveDateTime = match NaiveDateTime::parse_from_str(&key, NAIVE_DATE_TIME_FORMAT) {
+            Ok(res) => res,
+            Err(_) => {
+                return Err(invalid_key!("date time"));
+            }
+        };
+        Ok(res)
+    }
+
+    fn format_key(&self) -> String {
+        self.format(NAIVE_DATE_TIME_FORMAT).to_string()
+    }
+}
+
+impl KeyParse for i32 {
+    fn parse_key(key: String) -> Result<i32, PaginationError> {
+        let res: i32 = match key.parse::<i32>() {
+            Ok(res) => res,
+            Err(_) => {
+                return Err(invalid_key!("i32"));
+            }
+        };
+        Ok(res)
+    }
+
+    fn format_key(&self) -> String {
+        self.to_string()
+    }
+}
+
+impl KeyParse for i64 {
+    fn parse_key(key: String) -> Result<i64, PaginationError> {
+        let res: i64 = match key.parse::<i64>() {
+            Ok(res) => res,
+            Err(_) => {
+                return Err(invalid_key!("i64"));
+            }
+        };
+        Ok(res)
+    }
+
+    fn format_key(&self) -> String {
+        self.to_string()
+    }
+}
+
+/// Postgres has no unsigned integers, so sqlx doesn't bind `u32` there; store
+/// such columns as `BIGINT` and key on `i64` instead. MySQL binds it to
+/// `INT UNSIGNED` and SQLite to `INTEGER`.
+impl KeyParse for u32 {
+    fn parse_key(key: String) -> Result<u32, PaginationError> {
+        if key.starts_with('-') {
+            return Err(PaginationError::InvalidCursor(CursorError::InvalidKey(
+                "negative value for an unsigned key",
+            )));
+        }
+
+        match key.parse::<u32>() {
+            Ok(res) => Ok(res),
+            Err(_) => Err(invalid_key!("u32")),
+        }
+    }
+
+    fn format_key(&self) -> String {
+        self.to_string()
+    }
+}
+
+/// Like `u32`, sqlx doesn't bind `u64` on Postgres, where `BIGINT` with an `i64`
+/// key is the way to go. MySQL binds it to `BIGINT UNSIGNED`.
+impl KeyParse for u64 {
+    fn parse_key(key: String) -> Result<u64, PaginationError> {
+        if key.starts_with('-') {
+            return Err(PaginationError::InvalidCursor(CursorError::InvalidKey(
+                "negative value for an unsigned key",
+            )));
+        }
+
+        match key.parse::<u64>() {
+            Ok(res) => Ok(res),
+            Err(_) => Err(invalid_key!("u64")),
+        }
+    }
+
+    fn format_key(&self) -> String {
+        self.to_string()
+    }
+}
+
+/// Formats as `true`/`false`, and also parses Postgres' `t`/`f` and `1`/`0`.
+impl KeyParse for bool {
+    fn parse_key(key: String) -> Result<bool, PaginationError> {
+        match key.as_str() {
+            "true" | "t" | "1" => Ok(true),
+            "false" | "f" | "0" => Ok(false),
+            _ => Err(invalid_key!("bool")),
+        }
+    }
+
+    fn format_key(&self) -> String {
+        self.to_string()
+    }
+}
+
+#[cfg(feature = "uuid")]
+impl KeyParse for uuid::Uuid {
+    fn parse_key(key: String) -> Result<uuid::Uuid, PaginationError> {
+        let res: uuid::Uuid = match uuid::Uuid::parse_str(&key) {
+            Ok(res) => res,
+            Err(_) => {
+                return Err(invalid_key!("uuid"));
+            }
+        };
+        Ok(res)
+    }
+
+    fn format_key(&self) -> String {
+        self.hyphenated().to_string()
+    }
+}
+
+/// Parsed exactly, so the scale survives the round trip (`10.00` stays `10.00`).
+#[cfg(feature = "decimal")]
+impl KeyParse for rust_decimal::Decimal {
+    fn parse_key(key: String) -> Result<rust_decimal::Decimal, PaginationError> {
+        let res: rust_decimal::Decimal = match rust_decimal::Decimal::from_str_exact(&key) {
+            Ok(res) => res,
+            Err(_) => {
+                return Err(invalid_key!("decimal"));
+            }
+        };
+        Ok(res)
+    }
+
+    fn format_key(&self) -> String {
+        self.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use base64::{Engine, prelude::BASE64_URL_SAFE};
+    #[cfg(feature = "utoipa")]
+    use utoipa::PartialSchema;
+
+    use super::*;
+
+    #[derive(FromRow)]
+    struct User {
+        id: i64,
+        name: String,
+    }
+
+    #[test]
+    fn sort_by_allowed_column() {
+        let request = PaginationRequest {
+            sort_by: Some(String::from("name")),
+            ..Default::default()
+        };
+
+        let paginator = Paginator::<User>::new()
+            .keys("created_at", "id")
+            .allowed_sort_columns(&["created_at", "name"])
+            .retrieve_keys(|user: &User| (user.name.clone(), user.id.to_string()))
+            .request(&request);
+        assert_eq!(paginator.sort_keys().unwrap(), vec!["name", "id"]);
+
+        // without allowed columns sort_by is ignored
+        let paginator = Paginator::<User>::new()
+            .keys("created_at", "id")
+            .request(&request);
+        assert_eq!(paginator.sort_keys().unwrap(), vec!["created_at", "id"]);
+    }
+
+    #[test]
+    fn sort_by_unknown_column() {
+        let request = PaginationRequest {
+            sort_by: Some(String::from("name; DROP TABLE users")),
+            ..Default::default()
+        };
+
+        let paginator = Paginator::<User>::new()
+            .keys("created_at", "id")
+            .allowed_sort_columns(&["created_at", "name"])
+            .request(&request);
+        assert!(paginator.sort_keys().is_err());
+    }
+
+    #[cfg(feature = "utoipa")]
+    #[test]
+    fn openapi_examples() {
+        let params = serde_json::to_value(PaginationRequest::into_params(|| None)).unwrap();
+        let example = |name: &str| {
+            params
+                .as_array()
+                .unwrap()
+                .iter()
+                .find(|param| param["name"] == name)
+                .map(|param| param["example"].clone())
+        };
+        assert_eq!(example("limit"), Some(serde_json::json!(20)));
+        let cursor = example("cursor").unwrap();
+        assert!(decode_cursor(cursor.as_str().unwrap()).is_ok());
+
+        let schema = serde_json::to_value(PaginationResponse::<String>::schema()).unwrap();
+        let next_cursor = &schema["properties"]["next_cursor"]["example"];
+        assert!(decode_cursor(next_cursor.as_str().unwrap()).is_ok());
+    }
+
+    #[test]
+    fn with_total() {
+        let res = PaginationResponse::<()> {
+            applied_limit: 4,
+            ..Default::default()
+        };
+        let res = res.with_total(15);
+        assert_eq!(res.total, Some(15));
+        assert_eq!(res.total_pages, None);
+
+        let res = PaginationResponse::<()> {
+            page: Some(2),
+            applied_limit: 4,
+            ..Default::default()
+        };
+        assert_eq!(res.with_total(15).total_pages, Some(4));
+    }
+
+    #[test]
+    fn retryable_errors() {
+        let reset = std::io::Error::from(std::io::ErrorKind::ConnectionReset);
+        assert!(PaginationError::Database(sqlx::Error::Io(reset)).is_retryable());
+        assert!(PaginationError::Database(sqlx::Error::PoolTimedOut).is_retryable());
+
+        assert!(!PaginationError::Database(sqlx::Error::PoolClosed).is_retryable());
+        assert!(!PaginationError::Database(sqlx::Error::RowNotFound).is_retryable());
+        assert!(!PaginationError::InvalidCursor(CursorError::Tampered).is_retryable());
+    }
+
+    #[test]
+    fn sort_order_serde() {
+        assert_eq!(serde_json::to_string(&SortOrder::Desc).unwrap(), "\"desc\"");
+        assert!(serde_json::from_str::<SortOrder>("\"asc\"").unwrap() == SortOrder::Asc);
+
+        assert!(serde_json::from_str::<SortOrder>("\"DESC\"").unwrap() == SortOrder::Desc);
+        assert!(serde_json::from_str::<SortOrder>("\"ascending\"").unwrap() == SortOrder::Asc);
+        assert!(serde_json::from_str::<SortOrder>("\"-\"").unwrap() == SortOrder::Desc);
+
+        let err = serde_json::from_str::<SortOrder>("\"random\"").unwrap_err();
+        assert!(err.to_string().contains("invalid sort_order"));
+    }
+
+    #[test]
+    fn base_predicate() {
+        let request = PaginationRequest {
+            cursor: Some(encode_cursor(&[String::from("7")])),
+            ..Default::default()
+        };
+        let paginator = Paginator::<User>::new()
+            .single_key("id")
+            .retrieve_key(|user: &User| user.id.to_string())
+            .accept_legacy_cursors(true)
+            .request(&request);
+
+        let sql = paginator
+            .clone()
+            .dry_run::<(i64,)>(QueryBuilder::new(
+                "SELECT * FROM users WHERE deleted_at IS NULL",
+            ))
+            .unwrap();
+        assert!(sql.starts_with("SELECT * FROM users WHERE deleted_at IS NULL AND (id) < ($1)"));
+
+        let sql = paginator
+            .has_base_predicate(false)
+            .dry_run::<(i64,)>(QueryBuilder::new("SELECT * FROM users"))
+            .unwrap();
+        assert!(sql.starts_with("SELECT * FROM users WHERE (id) < ($1)"));
+    }
+
+    #[test]
+    fn error_kinds() {
+        assert!(matches!(
+            decode_cursor("not a cursor"),
+            Err(PaginationError::InvalidCursor(_))
+        ));
+        assert!(matches!(
+            i64::parse_key(String::from("abc")),
+            Err(PaginationError::InvalidCursor(_))
+        ));
+
+        let paginator = Paginator::<User>::new().keys("created_at", "id; --");
+        assert!(matches!(
+            paginator.sort_keys(),
+            Err(PaginationError::InvalidArgument(_))
+        ));
+
+        let paginator = Paginator::<User>::new().keys("name", "id");
+        assert!(matches!(
+            paginator.dry_run::<(String,)>(QueryBuilder::new("SELECT * FROM users WHERE TRUE")),
+            Err(PaginationError::Configuration(_))
+        ));
+    }
+
+    #[test]
+    fn parse_key_i32() {
+        let key = i32::parse_key(String::from("-42")).unwrap();
+        assert_eq!(key, -42);
+
+        assert!(i32::parse_key(String::from("2147483648")).is_err());
+        assert!(i32::parse_key(String::from("abc")).is_err());
+    }
+
+    #[test]
+    fn parse_key_i64() {
+        let key = i64::parse_key(String::from("-9000000000")).unwrap();
+        assert_eq!(key, -9000000000);
+
+        let key = i64::parse_key(i64::MAX.to_string()).unwrap();
+        assert_eq!(key, i64::MAX);
+
+        assert!(i64::parse_key(String::from("1.5")).is_err());
+    }
+
+    #[test]
+    fn parse_key_unsigned() {
+        assert_eq!(u32::parse_key(String::from("0")).unwrap(), 0);
+        assert_eq!(u32::parse_key(u32::MAX.to_string()).unwrap(), u32::MAX);
+        assert_eq!(u64::parse_key(u64::MAX.to_string()).unwrap(), u64::MAX);
+        round_trip(&[0u32, 1, u32::MAX]);
+        round_trip(&[0u64, 1, u64::MAX]);
+
+        assert!(matches!(
+            u32::parse_key(String::from("-1")),
+            Err(PaginationError::InvalidCursor(CursorError::InvalidKey(
+                "negative value for an unsigned key"
+            )))
+        ));
+        assert!(u64::parse_key(String::from("-1")).is_err());
+        assert!(u32::parse_key(String::from("4294967296")).is_err());
+    }
+
+    #[test]
+    fn parse_key_bool() {
+        round_trip(&[true, false]);
+
+        assert!(bool::parse_key(String::from("t")).unwrap());
+        assert!(!bool::parse_key(String::from("0")).unwrap());
+        assert!(bool::parse_key(String::from("maybe")).is_err());
+        assert!(bool::parse_key(String::from("TRUE")).is_err());
+    }
+
+    #[test]
+    fn identifiers() {
+        assert!(validate_identifier("id").is_ok());
+        assert!(validate_identifier("_created_at2").is_ok());
+        assert!(validate_identifier("users.id").is_ok());
+        assert!(validate_identifier("public.users.id").is_ok());
+
+        assert!(validate_identifier("").is_err());
+        assert!(validate_identifier("2id").is_err());
+        assert!(validate_identifier("users.").is_err());
+        assert!(validate_identifier("a.b.c.d").is_err());
+        assert!(validate_identifier("id\"; DROP TABLE users; --").is_err());
+        assert!(validate_identifier("id; DROP TABLE users").is_err());
+    }
+
+    #[test]
+    fn base_query_without_order_by_or_limit() {
+        let paginator = Paginator::<User>::new()
+            .single_key("id")
+            .retrieve_key(|user: &User| user.id.to_string());
+        let dry_run = |sql: &str| {
+            paginator
+                .clone()
+                .dry_run::<(i64,)>(QueryBuilder::new(sql))
+                .map(|_| ())
+        };
+
+        for sql in [
+            "SELECT * FROM users LIMIT 5",
+            "SELECT * FROM users WHERE TRUE order by name",
+            "SELECT * FROM users OFFSET 10",
+            "SELECT * FROM (SELECT * FROM users) AS u ORDER BY id LIMIT 5",
+            // a parenthesis in a comment or a dollar-quoted string doesn't nest
+            "SELECT * FROM users ORDER BY id /* ( */",
+            "SELECT * FROM users /* a /* nested ( */ comment */ LIMIT 5",
+            "SELECT $$($$ AS x FROM users LIMIT 5",
+        ] {
+            assert!(
+                matches!(dry_run(sql), Err(PaginationError::Configuration(_))),
+                "{}",
+                sql
+            );
+        }
+
+        for sql in [
+            "SELECT * FROM users WHERE id IN (SELECT user_id FROM logins ORDER BY at LIMIT 5)",
+            "SELECT string_agg(name, ',' ORDER BY name) FROM users GROUP BY id",
+            "SELECT * FROM users WHERE name = 'ORDER BY' AND \"limit\" > 0",
+            "SELECT * FROM users WHERE name = 'it''s' -- LIMIT 5\n AND TRUE",
+            "SELECT * FROM users WHERE limit_reached",
+            "SELECT id /* limit 5 */ FROM users WHERE TRUE",
+            "SELECT * FROM users WHERE bio = $$ORDER BY$$ AND note = $tag$LIMIT 5$tag$",
+            "SELECT * FROM users WHERE id = $1 AND TRUE",
+        ] {
+            assert!(dry_run(sql).is_ok(), "{}", sql);
+        }
+    }
+
+    #[test]
+    fn keys_are_validated() {
+        let paginator = Paginator::<User>::new().keys("created_at", "id; DROP TABLE users");
+        assert!(paginator.sort_keys().is_err());
+
+        let paginator = Paginator::<User>::new()
+            .keys("created_at", "user_id")
+            .key_column("user_id", "users.id; DROP TABLE users");
+        assert!(paginator.sort_keys().is_err());
+    }
+
+    #[test]
+    fn keys_are_distinct_and_not_empty() {
+        let paginator = Paginator::<User>::new().keys("id", "id");
+        assert!(matches!(
+            paginator.sort_keys(),
+            Err(PaginationError::InvalidArgument("duplicate key"))
+        ));
+
+        let paginator = Paginator::<User>::new().keys("", "x");
+        assert!(matches!(
+            paginator.sort_keys(),
+            Err(PaginationError::InvalidArgument("invalid key identifier"))
+        ));
+
+        let request = PaginationRequest {
+            sort_by: Some(String::from("id")),
+            ..Default::default()
+        };
+        let paginator = Paginator::<User>::new()
+            .keys("created_at", "id")
+            .allowed_sort_columns(&["created_at", "id"])
+            .request(&request);
+        assert!(paginator.sort_keys().is_err());
+    }
+
+    #[test]
+    fn key_column() {
+        let request = PaginationRequest {
+            cursor: Some(encode_cursor(&[String::from("42")])),
+            sort_order: Some(SortOrder::Asc),
+            ..Default::default()
+        };
+
+        let sql = Paginator::<User>::new()
+            .single_key("user_id")
+            .key_column("user_id", "users.id")
+            .retrieve_key(|user: &User| user.id.to_string())
+            .accept_legacy_cursors(true)
+            .request(&request)
+            .dry_run::<(i64,)>(QueryBuilder::new(
+                "SELECT users.id AS user_id FROM users \
+                 JOIN teams ON teams.id = users.team_id WHERE TRUE",
+            ))
+            .unwrap();
+        assert!(sql.ends_with(" AND (users.id) > ($1) ORDER BY users.id ASC LIMIT 11"));
+    }
+
+    #[test]
+    fn page_limit_defaults() {
+        assert_eq!(page_limit(None, 10, 100), 10);
+        assert_eq!(page_limit(Some(0), 10, 100), 0);
+        assert_eq!(page_limit(Some(20), 10, 100), 20);
+    }
+
+    #[test]
+    fn page_limit_clamps_to_max() {
+        assert_eq!(page_limit(Some(500), 10, 100), 100);
+        assert_eq!(page_limit(Some(5000), 20, 1000), 1000);
+        assert_eq!(page_limit(None, 50, 20), 20);
+    }
+
+    #[test]
+    fn finalize_page_splits_overshoot() {
+        // fewer rows than the limit
+        assert_eq!(finalize_page(vec![1, 2], 3), (vec![1, 2], None));
+        // exactly the limit
+        assert_eq!(finalize_page(vec![1, 2, 3], 3), (vec![1, 2, 3], None));
+        // the overshoot row
+        assert_eq!(finalize_page(vec![1, 2, 3, 4], 3), (vec![1, 2, 3], Some(4)));
+        assert_eq!(finalize_page(vec![1], 0), (vec![], Some(1)));
+        // the rows looked ahead over
+        assert_eq!(
+            finalize_page(vec![1, 2, 3, 4, 5], 3),
+            (vec![1, 2, 3], Some(4))
+        );
+    }
+
+    #[test]
+    fn pages_remaining_up_to_lookahead() {
+        assert_eq!(pages_remaining(0, 10, 3), 0);
+        assert_eq!(pages_remaining(1, 10, 3), 1);
+        assert_eq!(pages_remaining(10, 10, 3), 1);
+        assert_eq!(pages_remaining(11, 10, 3), 2);
+        assert_eq!(pages_remaining(21, 10, 3), 3);
+        assert_eq!(pages_remaining(100, 10, 3), 3);
+        assert_eq!(pages_remaining(5, 0, 3), 1);
+    }
+
+    #[test]
+    fn lookahead_pages() {
+        let paginator = Paginator::<User>::new()
+            .single_key("id")
+            .retrieve_key(|user: &User| user.id.to_string());
+
+        let sql = paginator
+            .clone()
+            .lookahead_pages(3)
+            .dry_run::<(i64,)>(QueryBuilder::new("SELECT * FROM users WHERE TRUE"))
+            .unwrap();
+        assert_eq!(
+            sql,
+            "SELECT * FROM users WHERE TRUE ORDER BY id DESC LIMIT 31"
+        );
+
+        let sql = paginator
+            .lookahead_pages(0)
+            .dry_run::<(i64,)>(QueryBuilder::new("SELECT * FROM users WHERE TRUE"))
+            .unwrap();
+        assert_eq!(
+            sql,
+            "SELECT * FROM users WHERE TRUE ORDER BY id DESC LIMIT 11"
+        );
+    }
+
+    #[test]
+    fn count_query_wraps_base_query() {
+        let count = count_query(|query: &mut QueryBuilder<Postgres>| {
+            query.push("SELECT id, name FROM users WHERE tenant_id = ");
+            query.push_bind(1_i64);
+        });
+
+        assert_eq!(
+            count.sql(),
+            "SELECT COUNT(*) FROM (SELECT id, name FROM users WHERE tenant_id = $1) AS base"
+        );
+    }
+
+    #[test]
+    fn page_bounds_forward() {
+        // first page
+        assert_eq!(page_bounds(true, false, false), (true, false));
+        // middle page
+        assert_eq!(page_bounds(true, true, false), (true, true));
+        // last page
+        assert_eq!(page_bounds(false, true, false), (false, true));
+    }
+
+    #[test]
+    fn page_bounds_backward() {
+        // middle page
+        assert_eq!(page_bounds(true, true, true), (true, true));
+        // backward onto the first page has no prev_cursor
+        assert_eq!(page_bounds(false, true, true), (true, false));
+    }
+
+    struct Task {
+        priority: i32,
+        name: String,
+        id: i64,
+    }
+
+    #[test]
+    fn cursor_three_keys() {
+        // closures may capture their surroundings
+        let prefix = String::from("task-");
+        let retrieve: RetrieveKeysFn<Task> = Arc::new(move |t: &Task| {
+            vec![
+                Some(t.priority.to_string()),
+                Some(format!("{}{}", prefix, t.name)),
+                Some(t.id.to_string()),
+            ]
+        });
+
+        let task = Task {
+            priority: 3,
+            name: String::from("backup"),
+            id: 7,
+        };
+
+        let cursor = CursorConfig::default().encode(retrieve(&task)).unwrap();
+
+        let values = CursorConfig::default().decode(cursor.clone(), 3).unwrap();
+        let (priority, name, id) =
+            <(i32, String, i64) as CursorKeys<Postgres>>::parse_keys(values).unwrap();
+        assert_eq!(priority, 3);
+        assert_eq!(name, "task-backup");
+        assert_eq!(id, 7);
+
+        assert!(CursorConfig::default().decode(cursor, 2).is_err());
+    }
+
+    #[test]
+    fn cursor_nullable_key() {
+        let cursor = CursorConfig::default()
+            .encode(vec![None, Some(String::from("7"))])
+            .unwrap();
+
+        let values = CursorConfig::default().decode(cursor.clone(), 2).unwrap();
+        let (archived_at, id) =
+            <(Option<String>, i64) as CursorKeys<Postgres>>::parse_keys(values).unwrap();
+        assert_eq!(archived_at, None);
+        assert_eq!(id, 7);
+
+        // non-nullable keys reject a NULL
+        let values = CursorConfig::default().decode(cursor, 2).unwrap();
+        assert!(<(String, i64) as CursorKeys<Postgres>>::parse_keys(values).is_err());
+    }
+
+    #[cfg(feature = "chrono")]
+    #[test]
+    fn parse_key_naive_date_time() {
+        for value in ["2024-01-02T03:04:05", "2024-01-02T03:04:05.678901"] {
+            let time: NaiveDateTime = value.parse().unwrap();
+            let key = NaiveDateTime::parse_key(time.to_string()).unwrap();
+            assert_eq!(key, time);
+        }
+
+        assert!(NaiveDateTime::parse_key(String::from("2024-01-02")).is_err());
+    }
+
+    fn round_trip<K: KeyParse + std::fmt::Debug + PartialEq>(keys: &[K]) {
+        for key in keys {
+            assert_eq!(&K::parse_key(key.format_key()).unwrap(), key);
+            assert_eq!(
+                &K::parse_nullable_key(key.format_nullable_key()).unwrap(),
+                key
+            );
+        }
+    }
+
+    #[cfg(feature = "chrono")]
+    #[test]
+    fn retrieve_typed_keys() {
+        struct Post {
+            id: i64,
+            created_at: DateTime<Utc>,
+            deleted_at: Option<DateTime<Utc>>,
+        }
+
+        let post = Post {
+            id: 7,
+            created_at: DateTime::from_timestamp(1_700_000_000, 123_456_789).unwrap(),
+            deleted_at: None,
+        };
+
+        let paginator = Paginator::<Post>::new()
+            .multi_keys(&["created_at", "deleted_at", "id"])
+            .retrieve_typed_keys(|post: &Post| (post.created_at, post.deleted_at, post.id));
+        let keys = (paginator.retrieve_keys_.as_ref().unwrap())(&post);
+        let cursor = paginator.cursor_.encode(keys).unwrap();
+
+        // the cursor's values parse back into the very same typed values
+        let mut values = paginator.cursor_.decode(cursor, 3).unwrap().into_iter();
+        assert_eq!(
+            DateTime::<Utc>::parse_nullable_key(values.next().unwrap()).unwrap(),
+            post.created_at
+        );
+        assert_eq!(
+            Option::<DateTime<Utc>>::parse_nullable_key(values.next().unwrap()).unwrap(),
+            None
+        );
+        assert_eq!(i64::parse_nullable_key(values.next().unwrap()).unwrap(), 7);
+    }
+
+    #[test]
+    fn format_key_round_trip() {
+        round_trip(&[String::new(), String::from("a, \"b\""), String::from("é")]);
+        round_trip(&[i32::MIN, -1, 0, 1, i32::MAX]);
+        round_trip(&[i64::MIN, -1, 0, 1, i64::MAX]);
+        round_trip(&[Some(42i64)]);
+        assert_eq!(Option::<i64>::None.format_nullable_key(), None);
+        assert_eq!(Option::<i64>::parse_nullable_key(None).unwrap(), None);
+
+        #[cfg(feature = "uuid")]
+        round_trip(&[uuid::Uuid::nil(), uuid::Uuid::from_u128(u128::MAX)]);
+
+        #[cfg(feature = "decimal")]
+        round_trip(&[
+            rust_decimal::Decimal::new(1000, 2),
+            rust_decimal::Decimal::new(-5, 0),
+            rust_decimal::Decimal::MAX,
+        ]);
+    }
+
+    #[cfg(feature = "chrono")]
+    #[test]
+    fn format_date_time_key_round_trip() {
+        let times: Vec<DateTime<Utc>> = [0, 1, 1_000, 1_000_000, 123_456_789]
+            .into_iter()
+            .map(|nanos| DateTime::from_timestamp(1_700_000_000, nanos).unwrap())
+            .collect();
+        round_trip(&times);
+        round_trip(
+            &times
+                .iter()
+                .map(|time| time.naive_utc())
+                .collect::<Vec<_>>(),
+        );
+        assert_eq!(times[4].format_key(), "2023-11-14T22:13:20.123456789Z");
+
+        let offset = FixedOffset::east_opt(7 * 3600).unwrap();
+        let local = DateTime::parse_from_rfc3339("2024-01-02T10:04:05.5+07:00").unwrap();
+        round_trip(&[local, times[4].with_timezone(&offset)]);
+        assert_eq!(local.format_key(), "2024-01-02T03:04:05.500Z");
+        assert_eq!(
+            DateTime::<FixedOffset>::parse_key(local.format_key()).unwrap(),
+            local
+        );
+
+        // ordered by instant, before a later UTC time with an earlier wall clock
+        let utc: DateTime<FixedOffset> = "2024-01-02T04:00:00Z".parse().unwrap();
+        assert!(local < utc);
+        assert!(local.format_key() < utc.format_key());
+        assert_eq!(
+            DateTime::<FixedOffset>::parse_key(local.to_rfc3339()).unwrap(),
+            local
+        );
+    }
+
+    #[cfg(feature = "uuid")]
+    #[test]
+    fn parse_key_uuid() {
+        let id = uuid::Uuid::parse_str("67e55044-10b1-426f-9247-bb680e5fe0c8").unwrap();
+        let key = uuid::Uuid::parse_key(id.to_string()).unwrap();
+        assert_eq!(key, id);
+
+        assert!(uuid::Uuid::parse_key(String::from("67e55044-10b1-426f")).is_err());
+    }
+
+    #[cfg(feature = "uuid")]
+    #[test]
+    fn parse_key_error_is_fixed() {
+        let payload = String::from("' OR 1=1; --");
+        match uuid::Uuid::parse_key(payload.clone()) {
+            Err(PaginationError::InvalidCursor(err)) => {
+                assert_eq!(
+                    err,
+                    CursorError::InvalidKey("cursor key is not a valid uuid")
+                );
+                assert!(!err.message().contains(&payload));
+            }
+            _ => panic!("parsed an invalid uuid"),
+        }
+    }
+
+    #[derive(Debug, PartialEq)]
+    enum Priority {
+        Low,
+        High,
+    }
+
+    impl FromStr for Priority {
+        type Err = ();
+
+        fn from_str(s: &str) -> Result<Priority, ()> {
+            match s {
+                "low" => Ok(Priority::Low),
+                "high" => Ok(Priority::High),
+                _ => Err(()),
+            }
+        }
+    }
+
+    impl fmt::Display for Priority {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            match self {
+                Priority::Low => write!(f, "low"),
+                Priority::High => write!(f, "high"),
+            }
+        }
+    }
+
+    from_str_key!(Priority);
+
+    #[test]
+    fn parse_key_from_str() {
+        round_trip(&[Priority::Low, Priority::High]);
+        round_trip(&[Some(Priority::High)]);
+        assert_eq!(Priority::High.format_key(), "high");
+
+        assert!(matches!(
+            Priority::parse_key(String::from("urgent")),
+            Err(PaginationError::InvalidCursor(CursorError::InvalidKey(
+                "cursor key is not a valid Priority"
+            )))
+        ));
+    }
+
+    #[cfg(feature = "decimal")]
+    #[test]
+    fn parse_key_decimal() {
+        let balance = rust_decimal::Decimal::from_str_exact("10.00").unwrap();
+        let config = CursorConfig::default();
+        let cursor = config.encode(vec![Some(balance.to_string())]).unwrap();
+
+        let key =
+            rust_decimal::Decimal::parse_nullable_key(config.decode(cursor, 1).unwrap().remove(0))
+                .unwrap();
+        assert_eq!(key.to_string(), "10.00");
+        assert_eq!(key.scale(), 2);
+
+        assert!(rust_decimal::Decimal::parse_key(String::from("ten")).is_err());
+    }
+
+    #[test]
+    fn dry_run() {
+        let request = PaginationRequest {
+            cursor: Some(encode_cursor(&[String::from("alice"), String::from("7")])),
+            limit: Some(20),
+            ..Default::default()
+        };
+
+        let sql = Paginator::<User>::new()
+            .keys("name", "id")
+            .retrieve_keys(|user: &User| (user.name.clone(), user.id.to_string()))
+            .accept_legacy_cursors(true)
+            .request(&request)
+            .dry_run::<(String, i64)>(QueryBuilder::new("SELECT * FROM users WHERE TRUE"))
+            .unwrap();
+        assert_eq!(
+            sql,
+            "SELECT * FROM users WHERE TRUE AND (name, id) < ($1, $2) \
+             ORDER BY name DESC, id DESC LIMIT 21"
+        );
+    }
+
+    #[cfg(feature = "mysql")]
+    #[test]
+    fn dry_run_on_mysql() {
+        let paginator = Paginator::<User, sqlx::MySql>::new()
+            .keys("name", "id")
+            .retrieve_keys(|user: &User| (user.name.clone(), user.id.to_string()));
+
+        let sql = paginator
+            .clone()
+            .dry_run::<(String, i64)>(QueryBuilder::new("SELECT * FROM users WHERE TRUE"))
+            .unwrap();
+        assert_eq!(
+            sql,
+            "SELECT * FROM users WHERE TRUE ORDER BY name DESC, id DESC LIMIT 11"
+        );
+
+        // MySQL would reject the NULLS LAST
+        assert!(matches!(
+            paginator
+                .nulls(NullsOrder::Last)
+                .dry_run::<(String, i64)>(QueryBuilder::new("SELECT * FROM users WHERE TRUE")),
+            Err(PaginationError::Configuration(_))
+        ));
+    }
+
+    #[test]
+    fn pre_bound_base_query() {
+        let request = PaginationRequest {
+            cursor: Some(encode_cursor(&[String::from("alice"), String::from("7")])),
+            ..Default::default()
+        };
+
+        let mut query = QueryBuilder::new("SELECT * FROM users WHERE tenant_id = ");
+        query.push_bind(1_i64);
+        query.push(" AND status = ");
+        query.push_bind("active");
+
+        let sql = Paginator::<User>::new()
+            .keys("name", "id")
+            .retrieve_keys(|user: &User| (user.name.clone(), user.id.to_string()))
+            .accept_legacy_cursors(true)
+            .request(&request)
+            .dry_run::<(String, i64)>(query)
+            .unwrap();
+        assert_eq!(
+            sql,
+            "SELECT * FROM users WHERE tenant_id = $1 AND status = $2 AND (name, id) < ($3, $4) \
+             ORDER BY name DESC, id DESC LIMIT 11"
+        );
+    }
+
+    #[test]
+    fn self_describing_cursors() {
+        let config = CursorConfig {
+            sort: Some(String::from("name asc,id asc")),
+            limit: Some(20),
+            ..Default::default()
+        };
+        let cursor = config
+            .encode(vec![Some(String::from("alice")), Some(String::from("7"))])
+            .unwrap();
+        let request = PaginationRequest {
+            cursor: Some(cursor),
+            ..Default::default()
+        };
+        let paginator = Paginator::<User>::new()
+            .keys("created_at", "id")
+            .retrieve_keys(|user: &User| (user.name.clone(), user.id.to_string()))
+            .allowed_sort_columns(&["created_at", "name"])
+            .self_describing_cursors(true);
+        let dry_run = |paginator: Paginator<User>, request: &PaginationRequest| {
+            paginator
+                .request(request)
+                .dry_run::<(String, i64)>(QueryBuilder::new("SELECT * FROM users WHERE TRUE"))
+        };
+
+        // the request only carries the cursor
+        let sql = dry_run(paginator.clone(), &request).unwrap();
+        assert_eq!(
+            sql,
+            "SELECT * FROM users WHERE TRUE AND (name, id) > ($1, $2) \
+             ORDER BY name ASC, id ASC LIMIT 21"
+        );
+
+        let limited = PaginationRequest {
+            limit: Some(5),
+            ..request.clone()
+        };
+        let sql = dry_run(paginator.clone(), &limited).unwrap();
+        assert!(sql.ends_with("LIMIT 6"), "{}", sql);
+
+        // an explicit sort still has to match the cursor's
+        let resorted = PaginationRequest {
+            sort_order: Some(SortOrder::Desc),
+            ..request.clone()
+        };
+        assert!(matches!(
+            dry_run(paginator.clone(), &resorted),
+            Err(PaginationError::InvalidCursor(CursorError::SortMismatch))
+        ));
+        assert!(matches!(
+            dry_run(paginator.self_describing_cursors(false), &request),
+            Err(PaginationError::InvalidCursor(CursorError::KeysMismatch))
+        ));
+    }
+
+    #[test]
+    fn first() {
+        let request = PaginationRequest {
+            cursor: Some(encode_cursor(&[String::from("7")])),
+            limit: Some(50),
+            ..Default::default()
+        };
+        let sql = Paginator::<User>::new()
+            .single_key("id")
+            .retrieve_key(|user: &User| user.id.to_string())
+            .request(&request)
+            .first(10)
+            .dry_run::<(i64,)>(QueryBuilder::new("SELECT * FROM users WHERE TRUE"))
+            .unwrap();
+        assert_eq!(
+            sql,
+            "SELECT * FROM users WHERE TRUE ORDER BY id DESC LIMIT 11"
+        );
+    }
+
+    #[test]
+    fn inclusive_cursor() {
+        let request = PaginationRequest {
+            cursor: Some(encode_cursor(&[String::from("7")])),
+            ..Default::default()
+        };
+        let paginator = Paginator::<User>::new()
+            .single_key("id")
+            .retrieve_key(|user: &User| user.id.to_string())
+            .accept_legacy_cursors(true)
+            .request(&request)
+            .inclusive_cursor(true);
+
+        let sql = paginator
+            .dry_run::<(i64,)>(QueryBuilder::new("SELECT * FROM users WHERE TRUE"))
+            .unwrap();
+        assert_eq!(
+            sql,
+            "SELECT * FROM users WHERE TRUE AND (id) <= ($1) ORDER BY id DESC LIMIT 11"
+        );
+    }
+
+    #[test]
+    fn collate() {
+        let request = PaginationRequest {
+            cursor: Some(encode_cursor(&[String::from("alice"), String::from("7")])),
+            ..Default::default()
+        };
+        let sql = Paginator::<User>::new()
+            .keys("name", "id")
+            .collate("name", "und-x-icu")
+            .retrieve_keys(|user: &User| (user.name.clone(), user.id.to_string()))
+            .accept_legacy_cursors(true)
+            .request(&request)
+            .dry_run::<(String, i64)>(QueryBuilder::new("SELECT * FROM users WHERE TRUE"))
+            .unwrap();
+        assert_eq!(
+            sql,
+            "SELECT * FROM users WHERE TRUE AND (name COLLATE \"und-x-icu\", id) < ($1, $2) \
+             ORDER BY name COLLATE \"und-x-icu\" DESC, id DESC LIMIT 11"
+        );
+
+        let res = Paginator::<User>::new()
+            .keys("name", "id")
+            .collate("name", "C\" OR TRUE --")
+            .retrieve_keys(|user: &User| (user.name.clone(), user.id.to_string()))
+            .dry_run::<(String, i64)>(QueryBuilder::new("SELECT * FROM users WHERE TRUE"));
+        assert!(matches!(
+            res,
+            Err(PaginationError::InvalidArgument("invalid key collation"))
+        ));
+    }
+
+    #[test]
+    fn seek_to() {
+        let sql = Paginator::<User>::new()
+            .keys("name", "id")
+            .retrieve_keys(|user: &User| (user.name.clone(), user.id.to_string()))
+            .seek_to(String::from("alice"), String::from("7"))
+            .dry_run::<(String, i64)>(QueryBuilder::new("SELECT * FROM users WHERE TRUE"))
+            .unwrap();
+        assert_eq!(
+            sql,
+            "SELECT * FROM users WHERE TRUE AND (name, id) <= ($1, $2) \
+             ORDER BY name DESC, id DESC LIMIT 11"
+        );
+
+        let res = Paginator::<User>::new()
+            .keys("name", "id")
+            .retrieve_keys(|user: &User| (user.name.clone(), user.id.to_string()))
+            .seek_to(String::from("alice"), String::from("seven"))
+            .dry_run::<(String, i64)>(QueryBuilder::new("SELECT * FROM users WHERE TRUE"));
+        assert!(matches!(res, Err(PaginationError::InvalidCursor(_))));
+    }
+
+    #[test]
+    fn filters() {
+        let request = PaginationRequest {
+            cursor: Some(encode_cursor(&[String::from("7")])),
+            ..Default::default()
+        };
+        let paginator = Paginator::<User>::new()
+            .single_key("id")
+            .retrieve_key(|user: &User| user.id.to_string())
+            .accept_legacy_cursors(true)
+            .request(&request)
+            .filter("deleted_at IS NULL")
+            .filter_with(|query| {
+                query.push("tenant_id = ").push_bind(1_i64);
+            });
+
+        let sql = paginator
+            .clone()
+            .dry_run::<(i64,)>(QueryBuilder::new("SELECT * FROM users WHERE TRUE"))
+            .unwrap();
+        assert_eq!(
+            sql,
+            "SELECT * FROM users WHERE TRUE AND (deleted_at IS NULL) AND (tenant_id = $1) \
+             AND (id) < ($2) ORDER BY id DESC LIMIT 11"
+        );
+
+        let sql = paginator
+            .has_base_predicate(false)
+            .dry_run::<(i64,)>(QueryBuilder::new("SELECT * FROM users"))
+            .unwrap();
+        assert_eq!(
+            sql,
+            "SELECT * FROM users WHERE (deleted_at IS NULL) AND (tenant_id = $1) \
+             AND (id) < ($2) ORDER BY id DESC LIMIT 11"
+        );
+    }
+
+    #[test]
+    fn filters_after_cursor() {
+        let paginator = Paginator::<User>::new()
+            .single_key("id")
+            .retrieve_key(|user: &User| user.id.to_string())
+            .accept_legacy_cursors(true)
+            .filter_after_cursor("author_id <> 3")
+            .has_base_predicate(false);
+
+        let sql = paginator
+            .clone()
+            .dry_run::<(i64,)>(QueryBuilder::new("SELECT * FROM users"))
+            .unwrap();
+        assert_eq!(
+            sql,
+            "SELECT * FROM users WHERE (author_id <> 3) ORDER BY id DESC LIMIT 11"
+        );
+
+        let request = PaginationRequest {
+            cursor: Some(encode_cursor(&[String::from("7")])),
+            ..Default::default()
+        };
+        let sql = paginator
+            .request(&request)
+            .filter("deleted_at IS NULL")
+            .dry_run::<(i64,)>(QueryBuilder::new("SELECT * FROM users"))
+            .unwrap();
+        assert_eq!(
+            sql,
+            "SELECT * FROM users WHERE (deleted_at IS NULL) AND (id) < ($1) \
+             AND (author_id <> 3) ORDER BY id DESC LIMIT 11"
+        );
+    }
+
+    #[test]
+    fn key_expression() {
+        let request = PaginationRequest {
+            cursor: Some(encode_cursor(&[String::from("alice"), String::from("7")])),
+            sort_order: Some(SortOrder::Asc),
+            ..Default::default()
+        };
+
+        let sql = Paginator::<User>::new()
+            .keys("sort_name", "id")
+            .key_expression("sort_name", "lower(name)")
+            .retrieve_keys(|user: &User| (user.name.to_lowercase(), user.id.to_string()))
+            .accept_legacy_cursors(true)
+            .request(&request)
+            .dry_run::<(String, i64)>(QueryBuilder::new(
+                "SELECT *, lower(name) AS sort_name FROM users WHERE TRUE",
+            ))
+            .unwrap();
+        assert_eq!(
+            sql,
+            "SELECT *, lower(name) AS sort_name FROM users WHERE TRUE \
+             AND (lower(name), id) > ($1, $2) ORDER BY lower(name) ASC, id ASC LIMIT 11"
+        );
+    }
+
+    #[test]
+    fn build() {
+        let request = PaginationRequest {
+            cursor: Some(encode_cursor(&[String::from("42")])),
+            sort_order: Some(SortOrder::Asc),
+            ..Default::default()
+        };
+
+        let query = Paginator::<User>::new()
+            .single_key("id")
+            .retrieve_key(|user: &User| user.id.to_string())
+            .accept_legacy_cursors(true)
+            .request(&request)
+            .build::<(i64,)>(QueryBuilder::new("SELECT * FROM users WHERE TRUE"))
+            .unwrap();
+        assert_eq!(
+            query.sql(),
+            "SELECT * FROM users WHERE TRUE AND (id) > ($1) ORDER BY id ASC LIMIT 11"
+        );
+
+        // options rebuilding the base query need a function pushing it
+        assert!(
+            Paginator::<User>::new()
+                .single_key("id")
+                .retrieve_key(|user: &User| user.id.to_string())
+                .with_total_count(true)
+                .build::<(i64,)>(QueryBuilder::new("SELECT * FROM users WHERE TRUE"))
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn first_cursor() {
+        let dry_run = |request: &PaginationRequest| {
+            Paginator::<User>::new()
+                .single_key("id")
+                .retrieve_key(|user: &User| user.id.to_string())
+                .accept_legacy_cursors(true)
+                .request(request)
+                .dry_run::<(i64,)>(QueryBuilder::new("SELECT * FROM users WHERE TRUE"))
+                .unwrap()
+        };
+
+        let first = PaginationRequest::default().with_cursor(Cursor::first());
+        assert_eq!(first.cursor, None);
+        assert_eq!(dry_run(&first), dry_run(&PaginationRequest::default()));
+        assert!(!dry_run(&first).contains("$1"));
+
+        let cursor = encode_cursor(&[String::from("42")]);
+        let next = PaginationRequest::default().with_cursor(Cursor::new(cursor.clone()));
+        assert_eq!(next.cursor, Some(cursor));
+        assert!(dry_run(&next).contains("(id) < ($1)"));
+
+        assert!(Cursor::from(None).is_first());
+    }
+
+    #[test]
+    fn validate_request() {
+        let request = PaginationRequest {
+            cursor: Some(String::from("not base64!")),
+            limit: Some(500),
+            ..Default::default()
+        };
+
+        let err = request.validate(100).unwrap_err();
+        assert!(matches!(
+            &err,
+            PaginationError::InvalidRequest(messages)
+                if *messages == ["cursor is not valid base64", "limit is too large"]
+        ));
+        assert_eq!(
+            err.to_string(),
+            "cursor is not valid base64, limit is too large"
+        );
+
+        let request = PaginationRequest {
+            cursor: Some(encode_cursor(&[String::from("42")])),
+            limit: Some(100),
+            page: Some(0),
+            ..Default::default()
+        };
+        assert!(matches!(
+            request.validate(100),
+            Err(PaginationError::InvalidRequest(messages)) if messages == ["page must be at least 1"]
+        ));
+
+        let request = PaginationRequest {
+            page: Some(1),
+            ..request
+        };
+        let validated = request.validate(100).unwrap();
+        let sql = Paginator::<User>::new()
+            .single_key("id")
+            .retrieve_key(|user: &User| user.id.to_string())
+            .request(&validated)
+            .dry_run::<(i64,)>(QueryBuilder::new("SELECT * FROM users WHERE TRUE"))
+            .unwrap();
+        assert!(sql.ends_with(" LIMIT 101"));
+    }
+
+    #[test]
+    fn cursor_for_requires_retrieve_keys() {
+        let user = User {
+            id: 7,
+            name: String::from("alice"),
+        };
+        let paginator = Paginator::<User>::new().single_key("id");
+        assert!(matches!(
+            paginator.cursor_for(&user),
+            Err(PaginationError::Configuration(_))
+        ));
+
+        let cursor = paginator
+            .retrieve_key(|user: &User| user.id.to_string())
+            .cursor_for(&user)
+            .unwrap();
+        assert_eq!(decode_cursor(&cursor).unwrap(), [String::from("7")]);
+    }
+
+    #[test]
+    fn normalize_keys() {
+        let user = User {
+            id: 7,
+            name: String::from("2024-01-02T03:04:05.678Z"),
+        };
+        let paginator = Paginator::<User>::new()
+            .keys("created_at", "id")
+            .retrieve_keys(|user: &User| (user.name.clone(), user.id.to_string()));
+
+        // truncated to the seconds the index keeps
+        let cursor = paginator
+            .clone()
+            .normalize_keys(|(created_at, id)| (format!("{}Z", &created_at[..19]), id))
+            .cursor_for(&user)
+            .unwrap();
+        let payload = CursorConfig::default().decode_unsorted(&cursor).unwrap();
+        assert_eq!(
+            payload.keys,
+            [
+                Some(String::from("2024-01-02T03:04:05Z")),
+                Some(String::from("7"))
+            ]
+        );
+
+        let cursor = paginator
+            .normalize_nullable_keys(|keys| keys.into_iter().rev().collect())
+            .cursor_for(&user)
+            .unwrap();
+        let payload = CursorConfig::default().decode_unsorted(&cursor).unwrap();
+        assert_eq!(payload.keys[0].as_deref(), Some("7"));
+    }
+
+    #[test]
+    fn cursor_bytes() {
+        let paginator = Paginator::<User>::new()
+            .keys("created_at", "id")
+            .retrieve_keys(|user: &User| (user.name.clone(), user.id.to_string()))
+            .cursor_encoding(CursorEncoding::StandardNoPad)
+            .signing_key(b"secret");
+
+        let mut config = paginator.cursor_.clone();
+        config.sort = Some(cursor_sort(
+            &[String::from("created_at"), String::from("id")],
+            &[true, true],
+        ));
+        let cursor = config
+            .encode(vec![Some(String::from("2024-01-02T03:04:05Z")), None])
+            .unwrap();
+
+        let bytes = paginator.cursor_to_bytes(&cursor).unwrap();
+        let from_bytes = paginator.cursor_from_bytes(&bytes);
+        assert_eq!(from_bytes, cursor);
+        assert_eq!(
+            paginator.validate_cursor(&from_bytes).unwrap(),
+            paginator.validate_cursor(&cursor).unwrap()
+        );
+
+        assert!(paginator.cursor_to_bytes("not base64!").is_err());
+    }
+
+    #[test]
+    fn validate_cursor() {
+        let paginator = Paginator::<User>::new()
+            .keys("created_at", "id")
+            .retrieve_keys(|user: &User| (user.name.clone(), user.id.to_string()))
+            .accept_legacy_cursors(true)
+            .request(&PaginationRequest {
+                sort_order: Some(SortOrder::Asc),
+                ..Default::default()
+            });
+        let err = |cursor: &str| match paginator.validate_cursor(cursor) {
+            Err(PaginationError::InvalidCursor(message)) => message,
+            res => panic!("unexpected {:?}", res),
+        };
+
+        let cursor = encode_cursor(&[String::from("2024-01-02T03:04:05Z"), String::from("7")]);
+        assert_eq!(
+            paginator.validate_cursor(&cursor).unwrap(),
+            vec![
+                Some(String::from("2024-01-02T03:04:05Z")),
+                Some(String::from("7"))
+            ]
+        );
+        assert!(paginator.cursor_is_valid(&cursor));
+
+        // a cursor without a sort is only taken as a legacy cursor
+        let strict = paginator.clone().accept_legacy_cursors(false);
+        assert!(matches!(
+            strict.validate_cursor(&cursor),
+            Err(PaginationError::InvalidCursor(CursorError::SortMismatch))
+        ));
+
+        assert_eq!(err("not base64!"), CursorError::Malformed);
+        assert!(!paginator.cursor_is_valid("not base64!"));
+        assert_eq!(
+            err(&encode_cursor(&[String::from("7")])),
+            CursorError::WrongLength
+        );
+        assert_eq!(
+            err(&BASE64_URL_SAFE.encode(b"\x02{}")),
+            CursorError::InvalidPayload
+        );
+
+        // a cursor issued while paging the other way
+        let mut config = paginator.cursor_.clone();
+        config.sort = Some(cursor_sort(
+            &[String::from("created_at"), String::from("id")],
+            &[true, true],
+        ));
+        let cursor = config
+            .encode(vec![
+                Some(String::from("2024-01-02T03:04:05Z")),
+                Some(String::from("7")),
+            ])
+            .unwrap();
+        assert_eq!(err(&cursor), CursorError::SortMismatch);
+    }
+
+    #[test]
+    fn check_tie_breaker() {
+        let paginator = Paginator::<User>::new().keys("created_at", "id");
+        assert!(paginator.check_tie_breaker(&["id"]).is_ok());
+
+        let paginator = Paginator::<User>::new().single_key("created_at");
+        assert!(matches!(
+            paginator.check_tie_breaker(&["id"]),
+            Err(PaginationError::Configuration(_))
+        ));
+    }
+
+    #[test]
+    fn limit_overrides_request() {
+        let request = PaginationRequest {
+            limit: Some(10),
+            ..Default::default()
+        };
+
+        let sql = Paginator::<User>::new()
+            .single_key("id")
+            .retrieve_key(|user: &User| user.id.to_string())
+            .request(&request)
+            .limit(50)
+            .dry_run::<(i64,)>(QueryBuilder::new("SELECT * FROM users WHERE TRUE"))
+            .unwrap();
+        assert!(sql.ends_with(" LIMIT 51"));
+
+        let sql = Paginator::<User>::new()
+            .single_key("id")
+            .retrieve_key(|user: &User| user.id.to_string())
+            .request(&request)
+            .limit(500)
+            .dry_run::<(i64,)>(QueryBuilder::new("SELECT * FROM users WHERE TRUE"))
+            .unwrap();
+        assert!(sql.ends_with(" LIMIT 101"));
+    }
+
+    #[test]
+    fn limit_does_not_overflow() {
+        assert_eq!(page_limit(Some(u32::MAX), 10, u32::MAX), MAX_PAGE_LIMIT);
+        assert_eq!(page_limit(None, u32::MAX, u32::MAX), MAX_PAGE_LIMIT);
+
+        let request = PaginationRequest {
+            limit: Some(u32::MAX),
+            ..Default::default()
+        };
+        let sql = Paginator::<User>::new()
+            .single_key("id")
+            .retrieve_key(|user: &User| user.id.to_string())
+            .request(&request)
+            .max_limit(u32::MAX)
+            .dry_run::<(i64,)>(QueryBuilder::new("SELECT * FROM users WHERE TRUE"))
+            .unwrap();
+        assert!(sql.ends_with(" LIMIT 100001"));
+    }
+
+    #[cfg(feature = "indexed")]
+    #[test]
+    fn to_indexed() {
+        let paginator = Paginator::<User>::new()
+            .keys("name", "id")
+            .retrieve_keys(|user: &User| (user.name.clone(), user.id.to_string()));
+        let user = |id: i64, name: &str| User {
+            id,
+            name: String::from(name),
+        };
+
+        let res = PaginationResponse {
+            data: vec![user(2, "bob"), user(1, "alice"), user(3, "carol")],
+            has_next_page: true,
+            next_cursor: Some(String::from("next")),
+            ..Default::default()
+        };
+        let page = paginator.to_indexed(res).unwrap();
+        assert_eq!(
+            page.data.keys().collect::<Vec<_>>(),
+            ["bob", "alice", "carol"]
+        );
+        assert_eq!(page.data["alice"].id, 1);
+        assert_eq!(page.next_cursor.as_deref(), Some("next"));
+        assert!(page.has_next_page);
+
+        let res = PaginationResponse {
+            data: vec![user(1, "alice"), user(2, "alice")],
+            ..Default::default()
+        };
+        assert!(matches!(
+            paginator.to_indexed(res),
+            Err(PaginationError::Configuration(_))
+        ));
+    }
+
+    #[cfg(feature = "relay")]
+    #[test]
+    fn to_connection() {
+        let paginator = Paginator::<User>::new()
+            .single_key("id")
+            .retrieve_key(|user: &User| user.id.to_string());
+
+        let res = PaginationResponse {
+            data: vec![
+                User {
+                    id: 1,
+                    name: String::from("alice"),
+                },
+                User {
+                    id: 2,
+                    name: String::from("bob"),
+                },
+            ],
+            has_next_page: true,
+            ..Default::default()
+        };
+
+        let parse_keys: ParseKeysFn =
+            |values| <(i64,) as CursorKeys<Postgres>>::parse_keys(values).map(drop);
+        let connection = paginator.to_connection(res, parse_keys).unwrap();
+        assert_eq!(connection.edges.len(), 2);
+        assert_eq!(connection.edges[1].node.name, "bob");
+
+        let cursors: Vec<Vec<String>> = connection
+            .edges
+            .iter()
+            .map(|edge| decode_cursor(&edge.cursor).unwrap())
+            .collect();
+        assert_eq!(cursors, vec![vec!["1"], vec!["2"]]);
+
+        let page_info = connection.page_info;
+        assert!(page_info.has_next_page);
+        assert!(!page_info.has_previous_page);
+        assert_eq!(
+            page_info.start_cursor.as_ref(),
+            Some(&connection.edges[0].cursor)
+        );
+        assert_eq!(
+            page_info.end_cursor.as_ref(),
+            Some(&connection.edges[1].cursor)
+        );
+    }
+
+    #[test]
+    fn debug_hides_secrets() {
+        let paginator = Paginator::<User>::new()
+            .single_key("id")
+            .retrieve_key(|user: &User| user.id.to_string())
+            .signing_key(b"hunter2");
+
+        let debug = format!("{:?}", paginator.clone());
+        assert!(debug.contains(r#"keys: ["id"]"#));
+        assert!(debug.contains(r#"retrieve_keys: Some("<fn>")"#));
+        assert!(!debug.contains("hunter2") && !debug.contains("104"));
+    }
 }
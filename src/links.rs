@@ -0,0 +1,131 @@
+use serde::Serialize;
+#[cfg(feature = "utoipa")]
+use utoipa::ToSchema;
+
+use crate::PaginationResponse;
+
+/// Pagination links in the shape of JSON:API, built by
+/// [`PaginationResponse::links`].
+#[derive(Clone, Debug, Serialize)]
+#[cfg_attr(feature = "utoipa", derive(ToSchema))]
+pub struct Links {
+    pub first: String,
+    pub prev: Option<String>,
+    pub next: Option<String>,
+}
+
+impl<T> PaginationResponse<T> {
+    /// Builds the links to the first, previous and next pages from the URL of the
+    /// current one, e.g. `https://api.example.com/users?filter=x&cursor=...`.
+    ///
+    /// The `cursor` and `direction` query parameters are replaced, `prev` pages
+    /// backward from [`PaginationResponse::prev_cursor`], and every other
+    /// parameter is kept as is.
+    pub fn links(&self, base_url: &str) -> Links {
+        let (url, fragment) = match base_url.split_once('#') {
+            Some((url, fragment)) => (url, Some(fragment)),
+            None => (base_url, None),
+        };
+        let (path, query) = url.split_once('?').unwrap_or((url, ""));
+
+        let params: Vec<&str> = query
+            .split('&')
+            .filter(|param| !param.is_empty())
+            .filter(|param| {
+                let name = param.split_once('=').map_or(*param, |(name, _)| name);
+                name != "cursor" && name != "direction"
+            })
+            .collect();
+
+        let link = |extra: &[(&str, &str)]| {
+            let mut params: Vec<String> = params.iter().map(|param| param.to_string()).collect();
+            for (name, value) in extra {
+                params.push(format!("{}={}", name, encode_query_value(value)));
+            }
+
+            let mut link = path.to_string();
+            if !params.is_empty() {
+                link.push('?');
+                link.push_str(&params.join("&"));
+            }
+            if let Some(fragment) = fragment {
+                link.push('#');
+                link.push_str(fragment);
+            }
+            link
+        };
+
+        Links {
+            first: link(&[]),
+            prev: self
+                .prev_cursor
+                .as_ref()
+                .map(|cursor| link(&[("cursor", cursor), ("direction", "backward")])),
+            next: self
+                .next_cursor
+                .as_ref()
+                .map(|cursor| link(&[("cursor", cursor)])),
+        }
+    }
+}
+
+/// Percent-encodes everything but the unreserved characters of RFC 3986, e.g. the
+/// `=` padding and the `+` and `/` of standard base64 cursors.
+fn encode_query_value(value: &str) -> String {
+    let mut encoded = String::with_capacity(value.len());
+    for byte in value.bytes() {
+        if byte.is_ascii_alphanumeric() || matches!(byte, b'-' | b'.' | b'_' | b'~') {
+            encoded.push(byte as char);
+        } else {
+            encoded.push_str(&format!("%{:02X}", byte));
+        }
+    }
+    encoded
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn keeps_other_params() {
+        let res = PaginationResponse::<()> {
+            next_cursor: Some(String::from("AnsiayI6WyI0MiJdfQ==")),
+            prev_cursor: Some(String::from("AnsiayI6WyI3Il19")),
+            ..Default::default()
+        };
+
+        let links = res.links("https://api.example.com/users?filter=x&cursor=old&limit=20");
+        assert_eq!(
+            links.first,
+            "https://api.example.com/users?filter=x&limit=20"
+        );
+        assert_eq!(
+            links.next.as_deref(),
+            Some("https://api.example.com/users?filter=x&limit=20&cursor=AnsiayI6WyI0MiJdfQ%3D%3D")
+        );
+        assert_eq!(
+            links.prev.as_deref(),
+            Some(
+                "https://api.example.com/users?filter=x&limit=20\
+                 &cursor=AnsiayI6WyI3Il19&direction=backward"
+            )
+        );
+    }
+
+    #[test]
+    fn without_query() {
+        let res = PaginationResponse::<()> {
+            next_cursor: Some(String::from("AnsiayI6WyI0MiJdfQ==")),
+            ..Default::default()
+        };
+
+        let links = res.links("/users");
+        assert_eq!(links.first, "/users");
+        assert_eq!(
+            links.next.as_deref(),
+            Some("/users?cursor=AnsiayI6WyI0MiJdfQ%3D%3D")
+        );
+        assert_eq!(links.prev, None);
+    }
+}
@@ -0,0 +1,2103 @@
+#![cfg(feature = "sqlite")]
+
+use sqlx::{
+    FromRow, QueryBuilder, Sqlite, SqlitePool,
+    sqlite::{SqliteConnectOptions, SqliteJournalMode, SqlitePoolOptions},
+};
+use sqlx_cursor_paginator::{
+    CursorError, PaginationError, PaginationRequest, Paginator, encode_cursor,
+};
+
+#[derive(FromRow)]
+struct Task {
+    id: i64,
+    name: String,
+}
+
+async fn setup() -> SqlitePool {
+    // every connection to an in-memory database gets its own database
+    let pool = SqlitePoolOptions::new()
+        .max_connections(1)
+        .connect("sqlite::memory:")
+        .await
+        .unwrap();
+
+    sqlx::query("CREATE TABLE tasks (id INTEGER PRIMARY KEY, name TEXT NOT NULL)")
+        .execute(&pool)
+        .await
+        .unwrap();
+
+    for id in 1..=15 {
+        sqlx::query("INSERT INTO tasks (id, name) VALUES (?, ?)")
+            .bind(id)
+            .bind(format!("task {}", id))
+            .execute(&pool)
+            .await
+            .unwrap();
+    }
+
+    pool
+}
+
+fn tasks(query: &mut QueryBuilder<Sqlite>) {
+    query.push("SELECT id, name FROM tasks WHERE TRUE");
+}
+
+async fn page(pool: &SqlitePool, request: &PaginationRequest) -> (Vec<Task>, Option<String>) {
+    let res = Paginator::<Task, Sqlite>::new()
+        .single_key("id")
+        .retrieve_key(|task: &Task| task.id.to_string())
+        .request(request)
+        .paginate_single_with::<i64, _>(pool, tasks)
+        .await
+        .unwrap();
+
+    (res.data, res.next_cursor)
+}
+
+#[derive(FromRow)]
+struct Entry {
+    id: i64,
+    rank: Option<i64>,
+}
+
+#[tokio::test]
+async fn walks_out_of_nulls_sorted_first() {
+    let pool = setup().await;
+
+    sqlx::query("CREATE TABLE entries (id INTEGER PRIMARY KEY, rank INTEGER)")
+        .execute(&pool)
+        .await
+        .unwrap();
+    for id in 1..=9i64 {
+        sqlx::query("INSERT INTO entries (id, rank) VALUES (?, ?)")
+            .bind(id)
+            .bind(if id % 3 == 0 { None } else { Some(id % 4) })
+            .execute(&pool)
+            .await
+            .unwrap();
+    }
+
+    // SQLite sorts NULLs first ascending, so the walk starts in them
+    let mut request = PaginationRequest {
+        limit: Some(2),
+        sort_order: Some(sqlx_cursor_paginator::SortOrder::Asc),
+        ..Default::default()
+    };
+    let mut walked = vec![];
+    loop {
+        let res = Paginator::<Entry, Sqlite>::new()
+            .keys("rank", "id")
+            .retrieve_nullable_keys(|entry: &Entry| {
+                vec![
+                    entry.rank.map(|rank| rank.to_string()),
+                    Some(entry.id.to_string()),
+                ]
+            })
+            .request(&request)
+            .paginate::<Option<i64>, i64>(
+                &pool,
+                QueryBuilder::new("SELECT id, rank FROM entries WHERE TRUE"),
+            )
+            .await
+            .unwrap();
+        walked.extend(res.data.iter().map(|entry| (entry.rank, entry.id)));
+        if res.next_cursor.is_none() {
+            break;
+        }
+        request.cursor = res.next_cursor;
+    }
+
+    let mut expected: Vec<(Option<i64>, i64)> = (1..=9)
+        .map(|id| (if id % 3 == 0 { None } else { Some(id % 4) }, id))
+        .collect();
+    expected.sort();
+    assert_eq!(walked, expected);
+}
+
+#[tokio::test]
+async fn paginates_across_two_pages() {
+    let pool = setup().await;
+
+    let request = PaginationRequest {
+        sort_order: Some(sqlx_cursor_paginator::SortOrder::Asc),
+        ..Default::default()
+    };
+    let (data, next_cursor) = page(&pool, &request).await;
+    assert_eq!(
+        data.iter().map(|t| t.id).collect::<Vec<_>>(),
+        (1..=10).collect::<Vec<_>>()
+    );
+    assert_eq!(data[0].name, "task 1");
+    assert!(next_cursor.is_some());
+
+    let request = PaginationRequest {
+        cursor: next_cursor,
+        ..request
+    };
+    let (data, next_cursor) = page(&pool, &request).await;
+    assert_eq!(
+        data.iter().map(|t| t.id).collect::<Vec<_>>(),
+        (11..=15).collect::<Vec<_>>()
+    );
+    assert!(next_cursor.is_none());
+}
+
+#[tokio::test]
+async fn first_rows_continue_with_next_cursor() {
+    let pool = setup().await;
+
+    let res = Paginator::<Task, Sqlite>::new()
+        .single_key("id")
+        .retrieve_key(|task: &Task| task.id.to_string())
+        .first(10)
+        .paginate_single_with::<i64, _>(&pool, tasks)
+        .await
+        .unwrap();
+    assert_eq!(
+        res.data.iter().map(|task| task.id).collect::<Vec<_>>(),
+        (6..=15).rev().collect::<Vec<_>>()
+    );
+    assert!(res.has_next_page);
+    assert!(!res.has_prev_page);
+
+    let request = PaginationRequest {
+        cursor: res.next_cursor,
+        ..Default::default()
+    };
+    let (data, next_cursor) = page(&pool, &request).await;
+    assert_eq!(
+        data.iter().map(|task| task.id).collect::<Vec<_>>(),
+        (1..=5).rev().collect::<Vec<_>>()
+    );
+    assert!(next_cursor.is_none());
+}
+
+#[tokio::test]
+async fn last_page_has_no_next_cursor() {
+    let pool = setup().await;
+
+    let paginate = |request: PaginationRequest, base_query: &'static str| {
+        let pool = pool.clone();
+        async move {
+            Paginator::<Task, Sqlite>::new()
+                .single_key("id")
+                .retrieve_key(|task: &Task| task.id.to_string())
+                .request(&request)
+                .paginate_single::<i64>(&pool, QueryBuilder::new(base_query))
+                .await
+                .unwrap()
+        }
+    };
+    let request = PaginationRequest {
+        sort_order: Some(sqlx_cursor_paginator::SortOrder::Asc),
+        limit: Some(15),
+        ..Default::default()
+    };
+
+    // no rows at all
+    let res = paginate(request.clone(), "SELECT id, name FROM tasks WHERE id > 100").await;
+    assert!(res.data.is_empty());
+    assert!(!res.has_next_page && !res.has_prev_page);
+    assert_eq!((res.next_cursor, res.prev_cursor), (None, None));
+
+    // as many rows as the limit, without an overshoot row
+    let all = "SELECT id, name FROM tasks WHERE TRUE";
+    let res = paginate(request.clone(), all).await;
+    assert_eq!(res.data.len(), 15);
+    assert!(!res.has_next_page);
+    assert_eq!(res.next_cursor, None);
+
+    // one row short of them
+    let res = paginate(
+        PaginationRequest {
+            limit: Some(14),
+            ..request.clone()
+        },
+        all,
+    )
+    .await;
+    assert!(res.has_next_page);
+    let cursor = res.next_cursor.unwrap();
+
+    // the last row ends the next page exactly
+    let res = paginate(
+        PaginationRequest {
+            cursor: Some(cursor),
+            limit: Some(1),
+            ..request.clone()
+        },
+        all,
+    )
+    .await;
+    assert_eq!(res.data.iter().map(|t| t.id).collect::<Vec<_>>(), vec![15]);
+    assert!(!res.has_next_page);
+    assert_eq!(res.next_cursor, None);
+    assert!(res.prev_cursor.is_some());
+}
+
+#[tokio::test]
+async fn looks_ahead_for_remaining_pages() {
+    let pool = setup().await;
+    for id in 16..=25 {
+        sqlx::query("INSERT INTO tasks (id, name) VALUES (?, ?)")
+            .bind(id)
+            .bind(format!("task {}", id))
+            .execute(&pool)
+            .await
+            .unwrap();
+    }
+
+    let mut request = PaginationRequest {
+        limit: Some(10),
+        sort_order: Some(sqlx_cursor_paginator::SortOrder::Asc),
+        ..Default::default()
+    };
+    let mut pages = vec![];
+    loop {
+        let res = Paginator::<Task, Sqlite>::new()
+            .single_key("id")
+            .retrieve_key(|task: &Task| task.id.to_string())
+            .request(&request)
+            .lookahead_pages(2)
+            .paginate_single_with::<i64, _>(&pool, tasks)
+            .await
+            .unwrap();
+        pages.push((
+            res.data.iter().map(|task| task.id).collect::<Vec<_>>(),
+            res.pages_remaining_at_least,
+        ));
+        if res.next_cursor.is_none() {
+            break;
+        }
+        request.cursor = res.next_cursor;
+    }
+
+    // the rows fetched ahead are still left out of the page
+    assert_eq!(
+        pages,
+        [
+            ((1..=10).collect::<Vec<_>>(), 2),
+            ((11..=20).collect::<Vec<_>>(), 1),
+            ((21..=25).collect::<Vec<_>>(), 0),
+        ]
+    );
+}
+
+#[tokio::test]
+async fn inclusive_cursor_starts_at_its_row() {
+    let pool = setup().await;
+
+    let page = |request: PaginationRequest, inclusive: bool| {
+        let pool = pool.clone();
+        async move {
+            let res = Paginator::<Task, Sqlite>::new()
+                .single_key("id")
+                .retrieve_key(|task: &Task| task.id.to_string())
+                .request(&request)
+                .inclusive_cursor(inclusive)
+                .paginate_single_with::<i64, _>(&pool, tasks)
+                .await
+                .unwrap();
+            let ids: Vec<i64> = res.data.iter().map(|task| task.id).collect();
+            (ids, res.next_cursor, res.prev_cursor)
+        }
+    };
+
+    let request = PaginationRequest {
+        limit: Some(3),
+        sort_order: Some(sqlx_cursor_paginator::SortOrder::Asc),
+        ..Default::default()
+    };
+    let (ids, next_cursor, _) = page(request.clone(), true).await;
+    assert_eq!(ids, [1, 2, 3]);
+
+    let request = PaginationRequest {
+        cursor: next_cursor,
+        ..request
+    };
+    let (ids, _, _) = page(request.clone(), false).await;
+    assert_eq!(ids, [4, 5, 6]);
+
+    // the row the cursor points at comes first
+    let (ids, _, prev_cursor) = page(request.clone(), true).await;
+    assert_eq!(ids, [3, 4, 5]);
+
+    // and last backward
+    let request = PaginationRequest {
+        cursor: prev_cursor,
+        direction: Some(sqlx_cursor_paginator::PageDirection::Backward),
+        ..request
+    };
+    let (ids, _, _) = page(request, true).await;
+    assert_eq!(ids, [1, 2, 3]);
+}
+
+#[tokio::test]
+async fn continues_with_cursor_limit_and_sort() {
+    let pool = setup().await;
+
+    let mut request = PaginationRequest {
+        limit: Some(4),
+        sort_order: Some(sqlx_cursor_paginator::SortOrder::Asc),
+        ..Default::default()
+    };
+    let mut pages = vec![];
+    loop {
+        let res = Paginator::<Task, Sqlite>::new()
+            .single_key("id")
+            .retrieve_key(|task: &Task| task.id.to_string())
+            .request(&request)
+            .self_describing_cursors(true)
+            .paginate_single_with::<i64, _>(&pool, tasks)
+            .await
+            .unwrap();
+        pages.push(res.data.iter().map(|task| task.id).collect::<Vec<_>>());
+        if res.next_cursor.is_none() {
+            break;
+        }
+        // the client passes nothing but the cursor on
+        request = PaginationRequest {
+            cursor: res.next_cursor,
+            ..Default::default()
+        };
+    }
+
+    assert_eq!(
+        pages,
+        [
+            vec![1, 2, 3, 4],
+            vec![5, 6, 7, 8],
+            vec![9, 10, 11, 12],
+            vec![13, 14, 15]
+        ]
+    );
+}
+
+#[tokio::test]
+async fn validates_self_describing_cursor_without_request() {
+    let pool = setup().await;
+
+    let paginator = Paginator::<Task, Sqlite>::new()
+        .single_key("id")
+        .retrieve_key(|task: &Task| task.id.to_string())
+        .self_describing_cursors(true);
+    let res = paginator
+        .clone()
+        .request(&PaginationRequest {
+            limit: Some(4),
+            sort_order: Some(sqlx_cursor_paginator::SortOrder::Asc),
+            ..Default::default()
+        })
+        .paginate_single_with::<i64, _>(&pool, tasks)
+        .await
+        .unwrap();
+    let cursor = res.next_cursor.unwrap();
+
+    // validated under the cursor's own asc sort, not the request's default desc
+    let request = PaginationRequest {
+        cursor: Some(cursor.clone()),
+        ..Default::default()
+    };
+    let paginator = paginator.request(&request);
+    assert_eq!(
+        paginator.validate_cursor(&cursor).unwrap(),
+        [Some(String::from("4"))]
+    );
+    assert!(paginator.cursor_is_valid(&cursor));
+
+    let res = paginator
+        .paginate_single_with::<i64, _>(&pool, tasks)
+        .await
+        .unwrap();
+    assert_eq!(res.data.first().map(|task| task.id), Some(5));
+}
+
+#[tokio::test]
+async fn cursor_for_row_matches_next_cursor() {
+    let pool = setup().await;
+
+    let request = PaginationRequest {
+        limit: Some(3),
+        sort_order: Some(sqlx_cursor_paginator::SortOrder::Asc),
+        ..Default::default()
+    };
+    let paginator = Paginator::<Task, Sqlite>::new()
+        .single_key("id")
+        .retrieve_key(|task: &Task| task.id.to_string())
+        .signing_key(b"secret")
+        .request(&request);
+
+    let res = paginator
+        .clone()
+        .paginate_single_with::<i64, _>(&pool, tasks)
+        .await
+        .unwrap();
+    let last = res.data.last().unwrap();
+    assert_eq!(paginator.cursor_for(last).ok(), res.next_cursor);
+
+    // a cursor for any row continues right after it
+    let row = Task {
+        id: 7,
+        name: String::from("task 7"),
+    };
+    let request = PaginationRequest {
+        cursor: Some(paginator.cursor_for(&row).unwrap()),
+        ..request
+    };
+    let res = paginator
+        .request(&request)
+        .paginate_single_with::<i64, _>(&pool, tasks)
+        .await
+        .unwrap();
+    assert_eq!(
+        res.data.iter().map(|task| task.id).collect::<Vec<_>>(),
+        [8, 9, 10]
+    );
+}
+
+#[tokio::test]
+async fn reports_applied_limit_and_sort() {
+    let pool = setup().await;
+
+    let request = PaginationRequest {
+        limit: Some(500),
+        ..Default::default()
+    };
+    let res = Paginator::<Task, Sqlite>::new()
+        .single_key("id")
+        .retrieve_key(|task: &Task| task.id.to_string())
+        .request(&request)
+        .max_limit(100)
+        .paginate_single_with::<i64, _>(&pool, tasks)
+        .await
+        .unwrap();
+    assert_eq!(res.applied_limit, 100);
+    assert_eq!(
+        res.applied_sort_order,
+        Some(sqlx_cursor_paginator::SortOrder::Desc)
+    );
+
+    let res = Paginator::<Task, Sqlite>::new()
+        .single_key("id")
+        .retrieve_key(|task: &Task| task.id.to_string())
+        .paginate_single_with::<i64, _>(&pool, tasks)
+        .await
+        .unwrap();
+    assert_eq!(res.applied_limit, 10);
+}
+
+#[tokio::test]
+async fn executes_built_query_on_pool_and_transaction() {
+    let pool = setup().await;
+
+    let paginator = Paginator::<Task, Sqlite>::new()
+        .single_key("id")
+        .retrieve_key(|task: &Task| task.id.to_string())
+        .request(&PaginationRequest {
+            sort_order: Some(sqlx_cursor_paginator::SortOrder::Asc),
+            limit: Some(3),
+            ..Default::default()
+        });
+    let build = || {
+        paginator
+            .clone()
+            .build::<(i64,)>(QueryBuilder::new("SELECT id, name FROM tasks WHERE TRUE"))
+            .unwrap()
+    };
+    let ids = |data: &[Task]| data.iter().map(|task| task.id).collect::<Vec<_>>();
+
+    let res = build().execute(&pool).await.unwrap();
+    assert_eq!(ids(&res.data), [1, 2, 3]);
+
+    // the transaction sees its own uncommitted delete
+    let mut transaction = pool.begin().await.unwrap();
+    sqlx::query("DELETE FROM tasks WHERE id = 2")
+        .execute(&mut *transaction)
+        .await
+        .unwrap();
+    let res = build().execute(&mut *transaction).await.unwrap();
+    assert_eq!(ids(&res.data), [1, 3, 4]);
+    assert!(res.next_cursor.is_some());
+    transaction.rollback().await.unwrap();
+
+    let res = build().execute(&pool).await.unwrap();
+    assert_eq!(ids(&res.data), [1, 2, 3]);
+}
+
+#[tokio::test]
+async fn counts_and_paginates_in_transaction() {
+    let pool = setup().await;
+
+    let mut transaction = pool.begin().await.unwrap();
+    sqlx::query("DELETE FROM tasks WHERE id % 3 = 0")
+        .execute(&mut *transaction)
+        .await
+        .unwrap();
+
+    let res = Paginator::<Task, Sqlite>::new()
+        .single_key("id")
+        .retrieve_key(|task: &Task| task.id.to_string())
+        .limit(100)
+        .with_total_count(true)
+        .paginate_single_with::<i64, _>(&mut *transaction, tasks)
+        .await
+        .unwrap();
+    // both see the transaction's delete
+    assert_eq!(res.total, Some(10));
+    assert_eq!(res.data.len(), 10);
+    assert!(res.data.iter().all(|task| task.id % 3 != 0));
+
+    let count = Paginator::<Task, Sqlite>::new()
+        .single_key("id")
+        .count(&mut *transaction, tasks)
+        .await
+        .unwrap();
+    assert_eq!(count, 10);
+    transaction.rollback().await.unwrap();
+}
+
+#[tokio::test]
+async fn offset_mode_jumps_to_page() {
+    let pool = setup().await;
+
+    let request = PaginationRequest {
+        sort_order: Some(sqlx_cursor_paginator::SortOrder::Asc),
+        page: Some(2),
+        ..Default::default()
+    };
+    let res = Paginator::<Task, Sqlite>::new()
+        .single_key("id")
+        .retrieve_key(|task: &Task| task.id.to_string())
+        .request(&request)
+        .offset_mode()
+        .with_total_count(true)
+        .paginate_keys_with::<(i64,), _>(&pool, |query| {
+            query.push("SELECT id, name FROM tasks WHERE TRUE");
+        })
+        .await
+        .unwrap();
+
+    assert_eq!(
+        res.data.iter().map(|t| t.id).collect::<Vec<_>>(),
+        (11..=15).collect::<Vec<_>>()
+    );
+    assert_eq!(res.page, Some(2));
+    assert_eq!(res.total, Some(15));
+    assert_eq!(res.total_pages, Some(2));
+    assert!(!res.has_next_page);
+    assert!(res.has_prev_page);
+    assert!(res.next_cursor.is_none());
+}
+
+#[tokio::test]
+async fn rejects_cursor_under_another_sort() {
+    let pool = setup().await;
+
+    let request = PaginationRequest {
+        sort_order: Some(sqlx_cursor_paginator::SortOrder::Asc),
+        ..Default::default()
+    };
+    let (_, next_cursor) = page(&pool, &request).await;
+
+    let request = PaginationRequest {
+        cursor: next_cursor,
+        sort_order: Some(sqlx_cursor_paginator::SortOrder::Desc),
+        ..Default::default()
+    };
+    let res = Paginator::<Task, Sqlite>::new()
+        .single_key("id")
+        .retrieve_key(|task: &Task| task.id.to_string())
+        .request(&request)
+        .paginate_single::<i64>(
+            &pool,
+            QueryBuilder::new("SELECT id, name FROM tasks WHERE TRUE"),
+        )
+        .await;
+    assert!(matches!(
+        res,
+        Err(PaginationError::InvalidCursor(CursorError::SortMismatch))
+    ));
+}
+
+#[tokio::test]
+async fn rejects_cursor_of_other_keys() {
+    let pool = setup().await;
+
+    let request = PaginationRequest {
+        sort_order: Some(sqlx_cursor_paginator::SortOrder::Asc),
+        ..Default::default()
+    };
+    let (_, next_cursor) = page(&pool, &request).await;
+
+    // a cursor of a paginator keyed on the id, replayed against one keyed on the name
+    let request = PaginationRequest {
+        cursor: next_cursor,
+        ..request
+    };
+    let res = Paginator::<Task, Sqlite>::new()
+        .single_key("name")
+        .retrieve_key(|task: &Task| task.name.clone())
+        .request(&request)
+        .paginate_single_with::<String, _>(&pool, tasks)
+        .await;
+    assert!(matches!(
+        res,
+        Err(PaginationError::InvalidCursor(CursorError::KeysMismatch))
+    ));
+
+    // a cursor without a sort can't tell which keys it's for
+    let request = PaginationRequest {
+        cursor: Some(encode_cursor(&[String::from("10")])),
+        ..request
+    };
+    let paginator = Paginator::<Task, Sqlite>::new()
+        .single_key("name")
+        .retrieve_key(|task: &Task| task.name.clone())
+        .request(&request);
+    let res = paginator
+        .clone()
+        .paginate_single_with::<String, _>(&pool, tasks)
+        .await;
+    assert!(matches!(
+        res,
+        Err(PaginationError::InvalidCursor(CursorError::SortMismatch))
+    ));
+
+    // unless legacy cursors are taken at their word
+    let res = paginator
+        .accept_legacy_cursors(true)
+        .paginate_single_with::<String, _>(&pool, tasks)
+        .await;
+    assert!(res.is_ok());
+}
+
+#[tokio::test]
+async fn counts_without_rows() {
+    let pool = setup().await;
+
+    let mut request = PaginationRequest {
+        limit: Some(4),
+        ..Default::default()
+    };
+
+    let mut rows = 0;
+    loop {
+        let (data, next_cursor) = page(&pool, &request).await;
+        rows += data.len() as u64;
+        if next_cursor.is_none() {
+            break;
+        }
+        request.cursor = next_cursor;
+    }
+
+    // the cursor and limit of the request don't narrow the count
+    let count = Paginator::<Task, Sqlite>::new()
+        .single_key("id")
+        .request(&request)
+        .count(&pool, tasks)
+        .await
+        .unwrap();
+    assert_eq!(count, rows);
+    assert_eq!(count, 15);
+}
+
+#[tokio::test]
+async fn seeks_to_record() {
+    let pool = setup().await;
+
+    let request = PaginationRequest {
+        sort_order: Some(sqlx_cursor_paginator::SortOrder::Asc),
+        limit: Some(10),
+        ..Default::default()
+    };
+    let res = Paginator::<Task, Sqlite>::new()
+        .single_key("id")
+        .retrieve_key(|task: &Task| task.id.to_string())
+        .seek_to_keys(vec![String::from("5")])
+        .request(&request)
+        .paginate_single_with::<i64, _>(&pool, tasks)
+        .await
+        .unwrap();
+
+    let ids: Vec<i64> = res.data.iter().map(|task| task.id).collect();
+    assert_eq!(ids, (5..15).collect::<Vec<_>>());
+    assert!(res.has_next_page && res.has_prev_page);
+}
+
+#[tokio::test]
+async fn maps_rows_keeping_cursors() {
+    #[derive(Debug, PartialEq)]
+    struct TaskName(String);
+
+    let pool = setup().await;
+
+    let paginator = Paginator::<Task, Sqlite>::new()
+        .single_key("id")
+        .retrieve_key(|task: &Task| task.id.to_string())
+        .request(&PaginationRequest {
+            sort_order: Some(sqlx_cursor_paginator::SortOrder::Asc),
+            limit: Some(3),
+            ..Default::default()
+        });
+    let res = paginator
+        .clone()
+        .paginate_single_with::<i64, _>(&pool, tasks)
+        .await
+        .unwrap();
+    let next_cursor = res.next_cursor.clone();
+
+    let res = res.map(|task| TaskName(task.name));
+    assert_eq!(
+        res.data,
+        vec![
+            TaskName(String::from("task 1")),
+            TaskName(String::from("task 2")),
+            TaskName(String::from("task 3")),
+        ]
+    );
+    assert_eq!(res.next_cursor, next_cursor);
+    assert!(res.has_next_page);
+
+    // the cursor retrieved from the rows before mapping resumes after task 3
+    let (data, _) = page(
+        &pool,
+        &PaginationRequest {
+            cursor: res.next_cursor,
+            sort_order: Some(sqlx_cursor_paginator::SortOrder::Asc),
+            limit: Some(3),
+            ..Default::default()
+        },
+    )
+    .await;
+    assert_eq!(data.iter().map(|t| t.id).collect::<Vec<_>>(), vec![4, 5, 6]);
+}
+
+#[tokio::test]
+async fn pages_backward_in_display_order() {
+    let pool = setup().await;
+
+    let paginator = Paginator::<Task, Sqlite>::new()
+        .single_key("id")
+        .retrieve_key(|task: &Task| task.id.to_string());
+    let mut request = PaginationRequest {
+        sort_order: Some(sqlx_cursor_paginator::SortOrder::Asc),
+        limit: Some(5),
+        ..Default::default()
+    };
+
+    let ids = |data: &[Task]| data.iter().map(|task| task.id).collect::<Vec<_>>();
+
+    // the ids and prev_cursor of every page
+    let mut pages = vec![];
+    loop {
+        let res = paginator
+            .clone()
+            .request(&request)
+            .paginate_single_with::<i64, _>(&pool, tasks)
+            .await
+            .unwrap();
+        pages.push((ids(&res.data), res.prev_cursor));
+        if res.next_cursor.is_none() {
+            break;
+        }
+        request.cursor = res.next_cursor;
+    }
+    assert_eq!(pages.len(), 3);
+
+    // back from the last page onto the middle one
+    request.cursor = pages[2].1.clone();
+    request.direction = Some(sqlx_cursor_paginator::PageDirection::Backward);
+
+    let res = paginator
+        .clone()
+        .request(&request)
+        .paginate_single_with::<i64, _>(&pool, tasks)
+        .await
+        .unwrap();
+    assert_eq!(ids(&res.data), pages[1].0);
+    assert_eq!(ids(&res.data), [6, 7, 8, 9, 10]);
+
+    let walked = paginator
+        .clone()
+        .request(&request)
+        .preserve_display_order(false)
+        .paginate_single_with::<i64, _>(&pool, tasks)
+        .await
+        .unwrap();
+    assert_eq!(ids(&walked.data), [10, 9, 8, 7, 6]);
+    assert_eq!(walked.next_cursor, res.next_cursor);
+    assert_eq!(walked.prev_cursor, res.prev_cursor);
+}
+
+#[cfg(feature = "tracing")]
+#[tokio::test]
+#[tracing_test::traced_test]
+async fn traces_page_without_cursor() {
+    let pool = setup().await;
+
+    let request = PaginationRequest {
+        sort_order: Some(sqlx_cursor_paginator::SortOrder::Asc),
+        limit: Some(5),
+        ..Default::default()
+    };
+    let (_, next_cursor) = page(&pool, &request).await;
+    let cursor = next_cursor.unwrap();
+
+    page(
+        &pool,
+        &PaginationRequest {
+            cursor: Some(cursor.clone()),
+            ..request
+        },
+    )
+    .await;
+
+    assert!(logs_contain("paginate{limit=5"));
+    assert!(logs_contain("has_cursor=false"));
+    assert!(logs_contain("has_cursor=true"));
+    assert!(logs_contain("fetched page rows=5 has_more=true"));
+    // neither the cursor nor the keys it carries
+    assert!(!logs_contain(&cursor));
+    assert!(!logs_contain("\"5\""));
+}
+
+#[tokio::test]
+async fn skips_count_with_cached_total() {
+    let pool = setup().await;
+
+    let paginator = Paginator::<Task, Sqlite>::new()
+        .single_key("id")
+        .retrieve_key(|task: &Task| task.id.to_string())
+        .with_total_count(true);
+
+    let res = paginator
+        .clone()
+        .paginate_single_with::<i64, _>(&pool, tasks)
+        .await
+        .unwrap();
+    assert_eq!(res.total, Some(15));
+
+    // a stale count the caller cached is reported as is, without counting again
+    let res = paginator
+        .cached_total(12)
+        .paginate_single_with::<i64, _>(&pool, tasks)
+        .await
+        .unwrap();
+    assert_eq!(res.total, Some(12));
+}
+
+#[tokio::test]
+async fn joins_page_and_count() {
+    let pool = setup().await;
+
+    let paginator = Paginator::<Task, Sqlite>::new()
+        .single_key("id")
+        .retrieve_key(|task: &Task| task.id.to_string());
+    let offset_paginator = paginator.clone().offset_mode().request(&PaginationRequest {
+        limit: Some(4),
+        page: Some(2),
+        ..Default::default()
+    });
+
+    for paginator in [paginator, offset_paginator] {
+        let sequential = paginator
+            .clone()
+            .with_total_count(true)
+            .paginate_single_with::<i64, _>(&pool, tasks)
+            .await
+            .unwrap();
+
+        // the pool has a single connection, which the futures take in turn
+        let (page, total) = paginator.paginate_and_count::<(i64,), _>(&pool, tasks);
+        let (page, total) = tokio::join!(page, total);
+        let joined = page.unwrap().with_total(total.unwrap());
+
+        let ids = |tasks: &[Task]| tasks.iter().map(|task| task.id).collect::<Vec<_>>();
+        assert_eq!(ids(&joined.data), ids(&sequential.data));
+        assert_eq!(joined.next_cursor, sequential.next_cursor);
+        assert_eq!(joined.total, Some(15));
+        assert_eq!(joined.total_pages, sequential.total_pages);
+    }
+}
+
+#[tokio::test]
+async fn classifies_retryable_errors() {
+    let pool = SqlitePoolOptions::new()
+        .max_connections(1)
+        .acquire_timeout(std::time::Duration::from_millis(50))
+        .connect("sqlite::memory:")
+        .await
+        .unwrap();
+
+    let paginator = Paginator::<Task, Sqlite>::new()
+        .single_key("id")
+        .retrieve_key(|task: &Task| task.id.to_string());
+
+    // a syntax error fails again however many times it's retried
+    let err = paginator
+        .clone()
+        .paginate_single_with::<i64, _>(&pool, |query: &mut QueryBuilder<Sqlite>| {
+            query.push("SELEC id, name FROM tasks WHERE TRUE");
+        })
+        .await
+        .unwrap_err();
+    assert!(matches!(err, PaginationError::Database(_)));
+    assert!(!err.is_retryable());
+
+    // while the only connection is taken, acquiring another times out
+    let conn = pool.acquire().await.unwrap();
+    let err = paginator
+        .paginate_single_with::<i64, _>(&pool, tasks)
+        .await
+        .unwrap_err();
+    assert!(err.is_retryable());
+    drop(conn);
+}
+
+#[cfg(feature = "indexed")]
+#[tokio::test]
+async fn indexes_rows_by_first_key() {
+    let pool = setup().await;
+
+    let request = PaginationRequest {
+        limit: Some(3),
+        ..Default::default()
+    };
+    let page = Paginator::<Task, Sqlite>::new()
+        .single_key("id")
+        .retrieve_key(|task: &Task| task.id.to_string())
+        .request(&request)
+        .paginate_indexed::<(i64,)>(
+            &pool,
+            QueryBuilder::new("SELECT id, name FROM tasks WHERE TRUE"),
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(page.data.keys().collect::<Vec<_>>(), ["15", "14", "13"]);
+    assert_eq!(page.data["14"].name, "task 14");
+    assert!(page.next_cursor.is_some());
+}
+
+#[tokio::test]
+async fn classifies_busy_database_as_retryable() {
+    let path = std::env::temp_dir().join(format!("paginator-busy-{}.db", std::process::id()));
+    let options = SqliteConnectOptions::new()
+        .filename(&path)
+        .create_if_missing(true)
+        .journal_mode(SqliteJournalMode::Delete)
+        .busy_timeout(std::time::Duration::ZERO);
+    let pool = SqlitePoolOptions::new()
+        .max_connections(2)
+        .connect_with(options)
+        .await
+        .unwrap();
+
+    sqlx::query("CREATE TABLE tasks (id INTEGER PRIMARY KEY, name TEXT NOT NULL)")
+        .execute(&pool)
+        .await
+        .unwrap();
+
+    // an exclusive transaction keeps readers out until it ends
+    let mut locker = pool.acquire().await.unwrap();
+    sqlx::query("BEGIN EXCLUSIVE")
+        .execute(&mut *locker)
+        .await
+        .unwrap();
+
+    let err = Paginator::<Task, Sqlite>::new()
+        .single_key("id")
+        .retrieve_key(|task: &Task| task.id.to_string())
+        .paginate_single_with::<i64, _>(&pool, tasks)
+        .await
+        .unwrap_err();
+    assert!(matches!(err, PaginationError::Database(_)));
+    assert!(err.is_retryable());
+
+    sqlx::query("ROLLBACK").execute(&mut *locker).await.unwrap();
+    drop(locker);
+    pool.close().await;
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[tokio::test]
+async fn excludes_rows_after_cursor() {
+    let pool = setup().await;
+
+    let page = |cursor: Option<String>, excluded: Vec<i64>| {
+        let pool = pool.clone();
+        async move {
+            let request = PaginationRequest {
+                cursor,
+                limit: Some(3),
+                sort_order: Some(sqlx_cursor_paginator::SortOrder::Asc),
+                ..Default::default()
+            };
+            Paginator::<Task, Sqlite>::new()
+                .single_key("id")
+                .retrieve_key(|task: &Task| task.id.to_string())
+                .request(&request)
+                .with_total_count(true)
+                .filter_after_cursor_with(move |query| {
+                    query.push("id NOT IN (");
+                    let mut ids = query.separated(", ");
+                    for id in &excluded {
+                        ids.push_bind(*id);
+                    }
+                    ids.push_unseparated(")");
+                })
+                .paginate_single_with::<i64, _>(&pool, tasks)
+                .await
+                .unwrap()
+        }
+    };
+    let ids = |data: &[Task]| data.iter().map(|task| task.id).collect::<Vec<_>>();
+
+    let res = page(None, vec![2]).await;
+    assert_eq!(ids(&res.data), [1, 3, 4]);
+    assert_eq!(res.total, Some(14));
+
+    // the middle row is skipped without a gap or a repeat, even once the row the
+    // cursor points at is excluded as well
+    let res = page(res.next_cursor, vec![2, 4, 6]).await;
+    assert_eq!(ids(&res.data), [5, 7, 8]);
+    assert_eq!(res.total, Some(12));
+
+    let res = page(res.next_cursor, vec![2, 4, 6]).await;
+    assert_eq!(ids(&res.data), [9, 10, 11]);
+}
+
+#[tokio::test]
+async fn requires_retrieve_keys() {
+    let pool = setup().await;
+
+    let res = Paginator::<Task, Sqlite>::default()
+        .single_key("id")
+        .paginate_single::<i64>(
+            &pool,
+            QueryBuilder::new("SELECT id, name FROM tasks WHERE TRUE"),
+        )
+        .await;
+    assert!(res.is_err());
+}
+
+#[tokio::test]
+async fn window_count_matches_overfetch() {
+    let pool = setup().await;
+
+    for window in [false, true] {
+        let mut request = PaginationRequest {
+            sort_order: Some(sqlx_cursor_paginator::SortOrder::Asc),
+            limit: Some(5),
+            ..Default::default()
+        };
+
+        let mut pages = vec![];
+        loop {
+            let mut paginator = Paginator::<Task, Sqlite>::new()
+                .single_key("id")
+                .retrieve_key(|task: &Task| task.id.to_string())
+                .request(&request);
+            if window {
+                paginator = paginator.detect_next_via_window();
+            }
+
+            let res = paginator
+                .paginate_keys_with::<(i64,), _>(&pool, |query| {
+                    query.push("SELECT id, name FROM tasks WHERE TRUE");
+                })
+                .await
+                .unwrap();
+
+            pages.push((res.data.len(), res.has_next_page));
+            if res.next_cursor.is_none() {
+                break;
+            }
+            request.cursor = res.next_cursor;
+        }
+
+        // 15 rows end exactly on the third page
+        assert_eq!(pages, vec![(5, true), (5, true), (5, false)]);
+    }
+}
+
+#[tokio::test]
+async fn zero_limit_probes_for_rows() {
+    let pool = setup().await;
+
+    let request = PaginationRequest {
+        sort_order: Some(sqlx_cursor_paginator::SortOrder::Asc),
+        limit: Some(5),
+        ..Default::default()
+    };
+    let (_, next_cursor) = page(&pool, &request).await;
+
+    let request = PaginationRequest {
+        cursor: next_cursor.clone(),
+        limit: Some(0),
+        ..request
+    };
+    let res = Paginator::<Task, Sqlite>::new()
+        .single_key("id")
+        .retrieve_key(|task: &Task| task.id.to_string())
+        .request(&request)
+        .paginate_single::<i64>(
+            &pool,
+            QueryBuilder::new("SELECT id, name FROM tasks WHERE TRUE"),
+        )
+        .await
+        .unwrap();
+
+    assert!(res.data.is_empty());
+    assert!(res.has_next_page);
+    // the probe doesn't move, its cursor resumes where the request was
+    assert_eq!(res.next_cursor, next_cursor);
+
+    // a first page has no cursor to resume from
+    let request = PaginationRequest {
+        cursor: None,
+        ..request
+    };
+    let res = Paginator::<Task, Sqlite>::new()
+        .single_key("id")
+        .retrieve_key(|task: &Task| task.id.to_string())
+        .request(&request)
+        .paginate_single::<i64>(
+            &pool,
+            QueryBuilder::new("SELECT id, name FROM tasks WHERE TRUE"),
+        )
+        .await;
+    assert!(matches!(res, Err(PaginationError::InvalidArgument(_))));
+}
+
+#[tokio::test]
+async fn paginates_scalar_ids() {
+    let pool = setup().await;
+
+    for window in [false, true] {
+        let mut request = PaginationRequest {
+            sort_order: Some(sqlx_cursor_paginator::SortOrder::Asc),
+            limit: Some(10),
+            ..Default::default()
+        };
+
+        let mut ids = vec![];
+        loop {
+            let mut paginator = Paginator::<i64, Sqlite>::new()
+                .single_key("id")
+                .request(&request);
+            if window {
+                paginator = paginator.detect_next_via_window();
+            }
+
+            let res = paginator
+                .paginate_scalar(&pool, QueryBuilder::new("SELECT id FROM tasks WHERE TRUE"))
+                .await
+                .unwrap();
+
+            ids.extend(res.data);
+            if res.next_cursor.is_none() {
+                break;
+            }
+            request.cursor = res.next_cursor;
+        }
+
+        assert_eq!(ids, (1..=15).collect::<Vec<_>>());
+    }
+}
+
+#[cfg(feature = "relay")]
+#[tokio::test]
+async fn paginates_connection() {
+    let pool = setup().await;
+
+    let request = PaginationRequest {
+        sort_order: Some(sqlx_cursor_paginator::SortOrder::Asc),
+        limit: Some(3),
+        ..Default::default()
+    };
+    let connection = Paginator::<Task, Sqlite>::new()
+        .single_key("id")
+        .retrieve_key(|task: &Task| task.id.to_string())
+        .request(&request)
+        .paginate_connection::<(i64,)>(
+            &pool,
+            QueryBuilder::new("SELECT id, name FROM tasks WHERE TRUE"),
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(
+        connection
+            .edges
+            .iter()
+            .map(|e| e.node.id)
+            .collect::<Vec<_>>(),
+        vec![1, 2, 3]
+    );
+    assert!(connection.page_info.has_next_page);
+    assert_eq!(
+        connection.page_info.end_cursor.as_ref(),
+        Some(&connection.edges[2].cursor)
+    );
+
+    // resuming from any edge's cursor continues right after it
+    let request = PaginationRequest {
+        cursor: Some(connection.edges[0].cursor.clone()),
+        ..request
+    };
+    let (data, _) = page(&pool, &request).await;
+    assert_eq!(data.iter().map(|t| t.id).collect::<Vec<_>>(), vec![2, 3, 4]);
+}
+
+#[tokio::test]
+async fn returns_row_cursors() {
+    let pool = setup().await;
+
+    let request = PaginationRequest {
+        sort_order: Some(sqlx_cursor_paginator::SortOrder::Asc),
+        limit: Some(4),
+        ..Default::default()
+    };
+    let res = Paginator::<Task, Sqlite>::new()
+        .single_key("id")
+        .retrieve_key(|task: &Task| task.id.to_string())
+        .request(&request)
+        .with_row_cursors(true)
+        .paginate_single::<i64>(
+            &pool,
+            QueryBuilder::new("SELECT id, name FROM tasks WHERE TRUE"),
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(res.cursors.len(), res.data.len());
+    assert_eq!(res.cursors.last(), res.next_cursor.as_ref());
+
+    let request = PaginationRequest {
+        cursor: Some(res.cursors[1].clone()),
+        ..request
+    };
+    let (data, _) = page(&pool, &request).await;
+    assert_eq!(data[0].id, 3);
+}
+
+#[derive(FromRow)]
+struct Item {
+    id: i64,
+    priority: i64,
+}
+
+#[tokio::test]
+async fn paginates_mixed_directions() {
+    let pool = setup().await;
+
+    sqlx::query("CREATE TABLE items (id INTEGER PRIMARY KEY, priority INTEGER NOT NULL)")
+        .execute(&pool)
+        .await
+        .unwrap();
+    for id in 1..=11i64 {
+        sqlx::query("INSERT INTO items (id, priority) VALUES (?, ?)")
+            .bind(id)
+            .bind(id % 3)
+            .execute(&pool)
+            .await
+            .unwrap();
+    }
+
+    let mut request = PaginationRequest {
+        limit: Some(4),
+        ..Default::default()
+    };
+
+    let mut rows = vec![];
+    loop {
+        let res = Paginator::<Item, Sqlite>::new()
+            .keys_with_order(&[
+                ("priority", sqlx_cursor_paginator::SortOrder::Asc),
+                ("id", sqlx_cursor_paginator::SortOrder::Desc),
+            ])
+            .retrieve_keys(|item: &Item| (item.priority.to_string(), item.id.to_string()))
+            .request(&request)
+            .paginate::<i64, i64>(
+                &pool,
+                QueryBuilder::new("SELECT id, priority FROM items WHERE TRUE"),
+            )
+            .await
+            .unwrap();
+
+        rows.extend(res.data.iter().map(|item| (item.priority, item.id)));
+        if res.next_cursor.is_none() {
+            break;
+        }
+        request.cursor = res.next_cursor;
+    }
+
+    let mut expected: Vec<(i64, i64)> = (1..=11).map(|id| (id % 3, id)).collect();
+    expected.sort_by(|a, b| a.0.cmp(&b.0).then(b.1.cmp(&a.1)));
+    assert_eq!(rows, expected);
+}
+
+#[derive(FromRow)]
+struct TenantTask {
+    id: i64,
+    priority: i64,
+}
+
+#[tokio::test]
+async fn binds_cursor_after_base_query_binds() {
+    let pool = setup().await;
+
+    sqlx::query(
+        "CREATE TABLE tenant_tasks (id INTEGER PRIMARY KEY, tenant_id INTEGER NOT NULL, \
+         status TEXT NOT NULL, priority INTEGER NOT NULL)",
+    )
+    .execute(&pool)
+    .await
+    .unwrap();
+    for id in 1..=24i64 {
+        sqlx::query(
+            "INSERT INTO tenant_tasks (id, tenant_id, status, priority) VALUES (?, ?, ?, ?)",
+        )
+        .bind(id)
+        .bind(id % 2)
+        .bind(if id % 3 == 0 { "done" } else { "open" })
+        .bind(id % 4)
+        .execute(&pool)
+        .await
+        .unwrap();
+    }
+
+    let mut request = PaginationRequest {
+        sort_order: Some(sqlx_cursor_paginator::SortOrder::Asc),
+        limit: Some(3),
+        ..Default::default()
+    };
+
+    let mut rows = vec![];
+    loop {
+        // SQLite's placeholders are positional, so binds in the wrong order would
+        // compare the cursor against the tenant filter's values
+        let mut query =
+            QueryBuilder::new("SELECT id, priority FROM tenant_tasks WHERE tenant_id = ");
+        query.push_bind(1_i64);
+        query.push(" AND status = ");
+        query.push_bind("open");
+
+        let res = Paginator::<TenantTask, Sqlite>::new()
+            .keys("priority", "id")
+            .retrieve_keys(|task: &TenantTask| (task.priority.to_string(), task.id.to_string()))
+            .request(&request)
+            .paginate::<i64, i64>(&pool, query)
+            .await
+            .unwrap();
+
+        rows.extend(res.data.iter().map(|task| (task.priority, task.id)));
+        if res.next_cursor.is_none() {
+            break;
+        }
+        request.cursor = res.next_cursor;
+    }
+
+    let mut expected: Vec<(i64, i64)> = (1..=24)
+        .filter(|id| id % 2 == 1 && id % 3 != 0)
+        .map(|id| (id % 4, id))
+        .collect();
+    expected.sort();
+    assert_eq!(rows, expected);
+}
+
+#[tokio::test]
+async fn cursor_row_never_reappears() {
+    let pool = setup().await;
+
+    sqlx::query("CREATE TABLE events (id INTEGER PRIMARY KEY, created_at INTEGER NOT NULL)")
+        .execute(&pool)
+        .await
+        .unwrap();
+    for id in 1..=9i64 {
+        // every created_at is shared by three rows
+        sqlx::query("INSERT INTO events (id, created_at) VALUES (?, ?)")
+            .bind(id)
+            .bind(id / 3)
+            .execute(&pool)
+            .await
+            .unwrap();
+    }
+
+    for sort_order in [
+        sqlx_cursor_paginator::SortOrder::Asc,
+        sqlx_cursor_paginator::SortOrder::Desc,
+    ] {
+        let mut request = PaginationRequest {
+            sort_order: Some(sort_order),
+            limit: Some(2),
+            ..Default::default()
+        };
+
+        let mut seen = vec![];
+        let mut last: Option<i64> = None;
+        loop {
+            let res = Paginator::<Event, Sqlite>::new()
+                .keys("created_at", "id")
+                .retrieve_keys(|event: &Event| (event.created_at.to_string(), event.id.to_string()))
+                .request(&request)
+                .paginate::<i64, i64>(
+                    &pool,
+                    QueryBuilder::new("SELECT id, created_at FROM events WHERE TRUE"),
+                )
+                .await
+                .unwrap();
+
+            // the row the cursor points at is excluded from the page after it
+            if let Some(last) = last {
+                assert!(res.data.iter().all(|event| event.id != last));
+            }
+            seen.extend(res.data.iter().map(|event| event.id));
+            last = res.data.last().map(|event| event.id);
+
+            if res.next_cursor.is_none() {
+                break;
+            }
+            request.cursor = res.next_cursor;
+        }
+
+        seen.sort();
+        assert_eq!(seen, (1..=9).collect::<Vec<_>>());
+    }
+}
+
+#[tokio::test]
+async fn rejects_base_query_without_key_column() {
+    let pool = setup().await;
+
+    sqlx::query("CREATE TABLE events (id INTEGER PRIMARY KEY, created_at INTEGER NOT NULL)")
+        .execute(&pool)
+        .await
+        .unwrap();
+    sqlx::query("INSERT INTO events (id, created_at) VALUES (1, 100)")
+        .execute(&pool)
+        .await
+        .unwrap();
+
+    let res = Paginator::<Event, Sqlite>::new()
+        .keys("created_at", "id")
+        .retrieve_keys(|event: &Event| (event.created_at.to_string(), event.id.to_string()))
+        .paginate::<i64, i64>(&pool, QueryBuilder::new("SELECT id FROM events WHERE TRUE"))
+        .await;
+    match res {
+        Err(PaginationError::Configuration(message)) => {
+            assert_eq!(
+                message,
+                "key column created_at is not selected by the base query"
+            )
+        }
+        _ => panic!("paginated without the key column"),
+    }
+}
+
+#[tokio::test]
+async fn rejects_base_query_with_limit() {
+    let pool = setup().await;
+
+    let res = Paginator::<Task, Sqlite>::new()
+        .single_key("id")
+        .retrieve_key(|task: &Task| task.id.to_string())
+        .paginate_single_with::<i64, _>(&pool, |query: &mut QueryBuilder<Sqlite>| {
+            query.push("SELECT id, name FROM tasks ORDER BY id LIMIT 5");
+        })
+        .await;
+    match res {
+        Err(PaginationError::Configuration(message)) => {
+            assert_eq!(
+                message,
+                "base query has its own ORDER BY, LIMIT or OFFSET, the paginator appends them"
+            )
+        }
+        _ => panic!("paginated a base query with a LIMIT"),
+    }
+}
+
+#[tokio::test]
+async fn rejects_retrieved_keys_that_dont_parse_back() {
+    let pool = setup().await;
+
+    sqlx::query("CREATE TABLE events (id INTEGER PRIMARY KEY, created_at INTEGER NOT NULL)")
+        .execute(&pool)
+        .await
+        .unwrap();
+    for id in 1..=3 {
+        sqlx::query("INSERT INTO events (id, created_at) VALUES (?, ?)")
+            .bind(id)
+            .bind(1_704_067_200 + id * 86_400)
+            .execute(&pool)
+            .await
+            .unwrap();
+    }
+
+    // a unix timestamp key retrieved as fractional seconds
+    let paginator = Paginator::<Event, Sqlite>::new()
+        .keys("created_at", "id")
+        .retrieve_keys(|event: &Event| (format!("{}.0", event.created_at), event.id.to_string()))
+        .request(&PaginationRequest {
+            limit: Some(2),
+            ..Default::default()
+        });
+    let query = || QueryBuilder::new("SELECT id, created_at FROM events WHERE TRUE");
+
+    let res = paginator
+        .clone()
+        .validate_cursor_roundtrip(true)
+        .paginate::<i64, i64>(&pool, query())
+        .await;
+    match res {
+        Err(PaginationError::Configuration(message)) => {
+            assert!(message.starts_with("retrieved cursor keys don't parse back"))
+        }
+        _ => panic!("issued a cursor that doesn't parse"),
+    }
+
+    // a single page, whose edges are the only cursors issued
+    #[cfg(feature = "relay")]
+    {
+        let res = paginator
+            .clone()
+            .request(&PaginationRequest {
+                limit: Some(100),
+                ..Default::default()
+            })
+            .validate_cursor_roundtrip(true)
+            .paginate_connection::<(i64, i64)>(&pool, query())
+            .await;
+        assert!(matches!(res, Err(PaginationError::Configuration(_))));
+    }
+
+    // without the check the next request is the one rejected
+    let res = paginator
+        .clone()
+        .validate_cursor_roundtrip(false)
+        .paginate::<i64, i64>(&pool, query())
+        .await
+        .unwrap();
+    let res = paginator
+        .request(&PaginationRequest {
+            cursor: res.next_cursor,
+            limit: Some(2),
+            ..Default::default()
+        })
+        .validate_cursor_roundtrip(false)
+        .paginate::<i64, i64>(&pool, query())
+        .await;
+    assert!(matches!(
+        res,
+        Err(PaginationError::InvalidCursor(CursorError::InvalidKey(
+            "cursor key is not a valid i64"
+        )))
+    ));
+}
+
+#[derive(FromRow)]
+struct Person {
+    id: i64,
+    name: String,
+    sort_name: String,
+}
+
+#[tokio::test]
+async fn paginates_lowercased_expression() {
+    let pool = setup().await;
+
+    sqlx::query("CREATE TABLE people (id INTEGER PRIMARY KEY, name TEXT NOT NULL)")
+        .execute(&pool)
+        .await
+        .unwrap();
+    for (id, name) in [
+        (1i64, "bob"),
+        (2, "Alice"),
+        (3, "Dave"),
+        (4, "carol"),
+        (5, "alice"),
+    ] {
+        sqlx::query("INSERT INTO people (id, name) VALUES (?, ?)")
+            .bind(id)
+            .bind(name)
+            .execute(&pool)
+            .await
+            .unwrap();
+    }
+
+    let mut request = PaginationRequest {
+        sort_order: Some(sqlx_cursor_paginator::SortOrder::Asc),
+        limit: Some(2),
+        ..Default::default()
+    };
+
+    let mut names = vec![];
+    loop {
+        let res = Paginator::<Person, Sqlite>::new()
+            .keys("sort_name", "id")
+            .key_expression("sort_name", "lower(name)")
+            .retrieve_keys(|person: &Person| (person.sort_name.clone(), person.id.to_string()))
+            .request(&request)
+            .paginate::<String, i64>(
+                &pool,
+                QueryBuilder::new(
+                    "SELECT id, name, lower(name) AS sort_name FROM people WHERE TRUE",
+                ),
+            )
+            .await
+            .unwrap();
+
+        names.extend(res.data.into_iter().map(|person| person.name));
+        if res.next_cursor.is_none() {
+            break;
+        }
+        request.cursor = res.next_cursor;
+    }
+
+    assert_eq!(names, ["Alice", "alice", "bob", "carol", "Dave"]);
+}
+
+#[tokio::test]
+async fn paginates_under_collation() {
+    let pool = setup().await;
+
+    sqlx::query("CREATE TABLE people (id INTEGER PRIMARY KEY, name TEXT NOT NULL)")
+        .execute(&pool)
+        .await
+        .unwrap();
+    for (id, name) in [
+        (1i64, "bob"),
+        (2, "Alice"),
+        (3, "Dave"),
+        (4, "carol"),
+        (5, "alice"),
+        (6, "BOB"),
+    ] {
+        sqlx::query("INSERT INTO people (id, name) VALUES (?, ?)")
+            .bind(id)
+            .bind(name)
+            .execute(&pool)
+            .await
+            .unwrap();
+    }
+
+    let mut request = PaginationRequest {
+        sort_order: Some(sqlx_cursor_paginator::SortOrder::Asc),
+        limit: Some(2),
+        ..Default::default()
+    };
+
+    let mut ids = vec![];
+    loop {
+        let res = Paginator::<Task, Sqlite>::new()
+            .keys("name", "id")
+            .collate("name", "NOCASE")
+            .retrieve_keys(|task: &Task| (task.name.clone(), task.id.to_string()))
+            .request(&request)
+            .paginate::<String, i64>(
+                &pool,
+                QueryBuilder::new("SELECT id, name FROM people WHERE TRUE"),
+            )
+            .await
+            .unwrap();
+
+        ids.extend(res.data.into_iter().map(|task| task.id));
+        if res.next_cursor.is_none() {
+            break;
+        }
+        request.cursor = res.next_cursor;
+    }
+
+    // under BINARY the uppercase names would all come first
+    assert_eq!(ids, [2, 5, 1, 6, 4, 3]);
+}
+
+#[tokio::test]
+async fn normalizes_keys_of_next_cursor() {
+    let pool = setup().await;
+
+    // cursors carry the uppercased names, which compare the same under NOCASE
+    let paginator = Paginator::<Task, Sqlite>::new()
+        .keys("name", "id")
+        .collate("name", "NOCASE")
+        .retrieve_keys(|task: &Task| (task.name.clone(), task.id.to_string()))
+        .normalize_keys(|(name, id)| (name.to_uppercase(), id));
+
+    let mut request = PaginationRequest {
+        limit: Some(4),
+        sort_order: Some(sqlx_cursor_paginator::SortOrder::Asc),
+        ..Default::default()
+    };
+    let mut ids = vec![];
+    loop {
+        let res = paginator
+            .clone()
+            .request(&request)
+            .paginate_with::<String, i64, _>(&pool, tasks)
+            .await
+            .unwrap();
+
+        ids.extend(res.data.iter().map(|task| task.id));
+        let Some(cursor) = res.next_cursor else {
+            break;
+        };
+
+        let last = res.data.last().unwrap();
+        assert_eq!(
+            paginator
+                .clone()
+                .request(&request)
+                .validate_cursor(&cursor)
+                .unwrap(),
+            [Some(last.name.to_uppercase()), Some(last.id.to_string())]
+        );
+        request.cursor = Some(cursor);
+    }
+
+    let mut expected: Vec<i64> = (1..=15).collect();
+    expected.sort_by_key(|id| format!("task {}", id));
+    assert_eq!(ids, expected);
+}
+
+#[derive(FromRow)]
+struct Ranked {
+    id: i64,
+    position: i64,
+}
+
+#[tokio::test]
+async fn paginates_cte_in_subquery() {
+    let pool = setup().await;
+
+    let cte = |query: &mut QueryBuilder<Sqlite>| {
+        query.push(
+            "WITH ranked AS (SELECT id, 16 - id AS position FROM tasks) SELECT * FROM ranked",
+        );
+    };
+    let paginator = Paginator::<Ranked, Sqlite>::new()
+        .keys("position", "id")
+        .retrieve_keys(|row: &Ranked| (row.position.to_string(), row.id.to_string()))
+        .filter("position > 3")
+        .wrap_subquery(true)
+        .with_total_count(true);
+
+    let mut request = PaginationRequest {
+        limit: Some(8),
+        sort_order: Some(sqlx_cursor_paginator::SortOrder::Asc),
+        ..Default::default()
+    };
+    let mut pages = vec![];
+    loop {
+        let res = paginator
+            .clone()
+            .request(&request)
+            .paginate_with::<i64, i64, _>(&pool, cte)
+            .await
+            .unwrap();
+        assert_eq!(res.total, Some(12));
+
+        pages.push(res.data.iter().map(|row| row.id).collect::<Vec<_>>());
+        if res.next_cursor.is_none() {
+            break;
+        }
+        request.cursor = res.next_cursor;
+    }
+    assert_eq!(pages, [vec![12, 11, 10, 9, 8, 7, 6, 5], vec![4, 3, 2, 1]]);
+
+    // a prebuilt query can't be wrapped
+    let res = paginator
+        .with_total_count(false)
+        .paginate::<i64, i64>(&pool, QueryBuilder::new("SELECT * FROM tasks"))
+        .await;
+    assert!(matches!(res, Err(PaginationError::Configuration(_))));
+}
+
+#[derive(FromRow)]
+struct Event {
+    id: i64,
+    created_at: i64,
+}
+
+async fn event_ids(
+    pool: &SqlitePool,
+    paginator: Paginator<Event, Sqlite>,
+    tie_breaker: bool,
+) -> Vec<i64> {
+    let mut request = PaginationRequest {
+        sort_order: Some(sqlx_cursor_paginator::SortOrder::Asc),
+        limit: Some(2),
+        ..Default::default()
+    };
+
+    let mut ids = vec![];
+    loop {
+        let paginator = paginator.clone().request(&request);
+        let query = QueryBuilder::new("SELECT id, created_at FROM events WHERE TRUE");
+        let res = if tie_breaker {
+            paginator.paginate::<i64, i64>(pool, query).await
+        } else {
+            paginator.paginate_single::<i64>(pool, query).await
+        }
+        .unwrap();
+
+        ids.extend(res.data.iter().map(|event| event.id));
+        if res.next_cursor.is_none() {
+            break;
+        }
+        request.cursor = res.next_cursor;
+    }
+    ids
+}
+
+#[tokio::test]
+async fn duplicate_keys_need_a_tie_breaker() {
+    let pool = setup().await;
+
+    sqlx::query("CREATE TABLE events (id INTEGER PRIMARY KEY, created_at INTEGER NOT NULL)")
+        .execute(&pool)
+        .await
+        .unwrap();
+    for (id, created_at) in [(1i64, 100i64), (2, 200), (3, 200), (4, 300)] {
+        sqlx::query("INSERT INTO events (id, created_at) VALUES (?, ?)")
+            .bind(id)
+            .bind(created_at)
+            .execute(&pool)
+            .await
+            .unwrap();
+    }
+
+    // the first page ends on one of the two rows created at 200, and the next page
+    // starts after 200, skipping the other one
+    let paginator = Paginator::<Event, Sqlite>::new()
+        .single_key("created_at")
+        .retrieve_key(|event: &Event| event.created_at.to_string());
+    assert!(paginator.check_tie_breaker(&["id"]).is_err());
+    assert_eq!(event_ids(&pool, paginator, false).await.len(), 3);
+
+    let paginator = Paginator::<Event, Sqlite>::new()
+        .keys("created_at", "id")
+        .retrieve_keys(|event: &Event| (event.created_at.to_string(), event.id.to_string()));
+    assert!(paginator.check_tie_breaker(&["id"]).is_ok());
+    assert_eq!(event_ids(&pool, paginator, true).await, [1, 2, 3, 4]);
+}
+
+#[derive(Clone, Debug, FromRow)]
+struct Post {
+    id: i64,
+    created_at: i64,
+    author: String,
+}
+
+/// Walks every page of the posts joined with their authors, selecting `select`.
+async fn post_ids(
+    pool: &SqlitePool,
+    paginator: Paginator<Post, Sqlite>,
+    select: &'static str,
+) -> Vec<i64> {
+    let mut request = PaginationRequest {
+        sort_order: Some(sqlx_cursor_paginator::SortOrder::Asc),
+        limit: Some(2),
+        ..Default::default()
+    };
+
+    let mut ids = vec![];
+    loop {
+        let res = paginator
+            .clone()
+            .request(&request)
+            .paginate_with::<i64, i64, _>(pool, |query| {
+                query.push(select);
+                query.push(" FROM posts a JOIN authors b ON b.id = a.author_id WHERE TRUE");
+            })
+            .await
+            .unwrap();
+
+        ids.extend(res.data.iter().map(|post| post.id));
+        if res.next_cursor.is_none() {
+            break;
+        }
+        request.cursor = res.next_cursor;
+    }
+    ids
+}
+
+#[tokio::test]
+async fn paginates_join_on_qualified_keys() {
+    let pool = setup().await;
+
+    // both tables have an id and a created_at, so unqualified keys are ambiguous
+    sqlx::query("CREATE TABLE authors (id INTEGER PRIMARY KEY, created_at INTEGER, name TEXT)")
+        .execute(&pool)
+        .await
+        .unwrap();
+    sqlx::query(
+        "CREATE TABLE posts (id INTEGER PRIMARY KEY, created_at INTEGER, author_id INTEGER)",
+    )
+    .execute(&pool)
+    .await
+    .unwrap();
+    for id in 1..=2i64 {
+        sqlx::query("INSERT INTO authors (id, created_at, name) VALUES (?, ?, ?)")
+            .bind(id)
+            .bind(id)
+            .bind(format!("author {}", id))
+            .execute(&pool)
+            .await
+            .unwrap();
+    }
+    for id in 1..=5i64 {
+        sqlx::query("INSERT INTO posts (id, created_at, author_id) VALUES (?, ?, ?)")
+            .bind(id)
+            .bind(100 - id / 2)
+            .bind(id % 2 + 1)
+            .execute(&pool)
+            .await
+            .unwrap();
+    }
+
+    let paginator = Paginator::<Post, Sqlite>::new()
+        .keys("a.created_at", "a.id")
+        .retrieve_keys(|post: &Post| (post.created_at.to_string(), post.id.to_string()));
+    let expected = vec![4, 5, 2, 3, 1];
+    assert_eq!(
+        post_ids(
+            &pool,
+            paginator,
+            "SELECT a.id, a.created_at, b.name AS author"
+        )
+        .await,
+        expected
+    );
+
+    // the same keys selected under other names, also ordering the window subquery
+    let paginator = Paginator::<Post, Sqlite>::new()
+        .keys("post_created_at", "post_id")
+        .key_column("post_created_at", "a.created_at")
+        .key_column("post_id", "a.id")
+        .detect_next_via_window()
+        .retrieve_keys(|post: &Post| (post.created_at.to_string(), post.id.to_string()));
+    assert_eq!(
+        post_ids(
+            &pool,
+            paginator,
+            "SELECT a.id, a.created_at, a.id AS post_id, a.created_at AS post_created_at, \
+             b.name AS author"
+        )
+        .await,
+        expected
+    );
+}
+
+#[tokio::test]
+async fn returns_peeked_row() {
+    let pool = setup().await;
+
+    let request = PaginationRequest {
+        sort_order: Some(sqlx_cursor_paginator::SortOrder::Asc),
+        limit: Some(6),
+        ..Default::default()
+    };
+    let res = Paginator::<Task, Sqlite>::new()
+        .single_key("id")
+        .retrieve_key(|task: &Task| task.id.to_string())
+        .request(&request)
+        .with_peeked_row(true)
+        .paginate_single::<i64>(
+            &pool,
+            QueryBuilder::new("SELECT id, name FROM tasks WHERE TRUE"),
+        )
+        .await
+        .unwrap();
+    assert_eq!(res.data.len(), 6);
+    assert!(res.has_next_page);
+    let peeked = res.peeked_row.unwrap();
+
+    let request = PaginationRequest {
+        cursor: res.next_cursor,
+        ..request
+    };
+    let (data, _) = page(&pool, &request).await;
+    assert_eq!(data[0].id, peeked.id);
+    assert_eq!(data[0].name, peeked.name);
+}
+
+#[tokio::test]
+async fn streams_every_page() {
+    use futures_util::StreamExt;
+
+    let pool = setup().await;
+    for id in 16..=25 {
+        sqlx::query("INSERT INTO tasks (id, name) VALUES (?, ?)")
+            .bind(id)
+            .bind(format!("task {}", id))
+            .execute(&pool)
+            .await
+            .unwrap();
+    }
+
+    let request = PaginationRequest {
+        sort_order: Some(sqlx_cursor_paginator::SortOrder::Asc),
+        limit: Some(10),
+        ..Default::default()
+    };
+    let pages: Vec<Vec<Task>> = Paginator::<Task, Sqlite>::new()
+        .single_key("id")
+        .retrieve_key(|task: &Task| task.id.to_string())
+        .request(&request)
+        .paginate_stream::<(i64,), _>(&pool, |query| {
+            query.push("SELECT id, name FROM tasks WHERE TRUE");
+        })
+        .map(Result::unwrap)
+        .collect()
+        .await;
+
+    assert_eq!(
+        pages.iter().map(Vec::len).collect::<Vec<_>>(),
+        vec![10, 10, 5]
+    );
+    assert_eq!(pages[2].last().unwrap().id, 25);
+}
+
+#[tokio::test]
+async fn collects_every_row() {
+    let pool = setup().await;
+
+    let request = PaginationRequest {
+        sort_order: Some(sqlx_cursor_paginator::SortOrder::Asc),
+        limit: Some(4),
+        ..Default::default()
+    };
+    let paginator = Paginator::<Task, Sqlite>::new()
+        .single_key("id")
+        .retrieve_key(|task: &Task| task.id.to_string())
+        .request(&request);
+
+    let rows = paginator
+        .clone()
+        .paginate_all::<(i64,), _>(
+            &pool,
+            |query| {
+                query.push("SELECT id, name FROM tasks WHERE TRUE");
+            },
+            15,
+        )
+        .await
+        .unwrap();
+    assert_eq!(
+        rows.iter().map(|t| t.id).collect::<Vec<_>>(),
+        (1..=15).collect::<Vec<_>>()
+    );
+
+    let res = paginator
+        .paginate_all::<(i64,), _>(
+            &pool,
+            |query| {
+                query.push("SELECT id, name FROM tasks WHERE TRUE");
+            },
+            10,
+        )
+        .await;
+    assert!(matches!(res, Err(PaginationError::TooManyRows(10))));
+}